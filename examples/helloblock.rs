@@ -101,15 +101,14 @@ fn hellokeys_thread_main(bc: BlockClient) {
     let mut reply_data: Vec<Value> = vec![];
     loop {
         let reply = {
-            let ctx = context.get_ctx().lock().unwrap();
-            ctx.call("SCAN", None, &[&cursor.to_string()]).unwrap()
+            let mut ctx = context.get_ctx().lock().unwrap();
+            ctx.call("SCAN", None, &[cursor]).unwrap()
         };
         let cr_cursor = reply.get_array_element(0).unwrap();
         let cr_keys = reply.get_array_element(1).unwrap();
         cursor = cr_cursor.get_string().unwrap().parse::<i32>().unwrap();
-        let items = cr_keys.get_length();
-        for i in 0..items {
-            reply_data.push(RResult::from(cr_keys.get_array_element(i).unwrap().into()).unwrap())
+        for key in cr_keys {
+            reply_data.push(RResult::from(key).unwrap())
         }
         if cursor == 0 {
             break;
@@ -32,12 +32,12 @@ extern "C" fn flushdb_callback_c(
     subevent: u64,
     data: *mut ::std::os::raw::c_void,
 ) {
-    let context = Context::from_ptr(ctx);
+    let mut context = Context::from_ptr(ctx);
     let ci: &mut raw::RedisModuleFlushInfo =
         unsafe { &mut *(data as *mut raw::RedisModuleFlushInfo) };
     if subevent == raw::REDISMODULE_SUBEVENT_FLUSHDB_START as u64 {
         if ci.dbnum != -1 {
-            let reply = context.call("DBSIZE", None, &vec![]).unwrap();
+            let reply = call!(context, "DBSIZE").unwrap();
             let num_keys = reply.get_integer().unwrap();
             println!(
                 "FLUSHDB event of database {} started ({} keys in DB)\n",
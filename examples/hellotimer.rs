@@ -4,7 +4,7 @@ use iredismodule_macros::rcmd;
 use rand::random;
 use std::time::Duration;
 
-fn timer_handler(ctx: &Context, data: String) {
+fn timer_handler(ctx: &mut Context, data: String) {
     ctx.notice(&data);
 }
 
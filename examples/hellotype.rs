@@ -1,4 +1,4 @@
-use iredismodule::io::{Digest, IO};
+use iredismodule::io::{AofBatcher, Digest, IO};
 use iredismodule::prelude::*;
 use iredismodule::rtype::TypeMethod;
 use iredismodule_macros::{rcmd, rtypedef, rwrap};
@@ -61,17 +61,17 @@ impl<'a> Iterator for HelloTypeNodeIter<'a> {
 /// ========================== "hellotype" type methods =======================
 #[rtypedef("hellotype", 0)]
 impl TypeMethod for HelloTypeNode {
-    fn rdb_load(io: &mut IO, encver: u32) -> Option<Box<Self>> {
+    fn rdb_load(io: &mut IO, encver: u32) -> Result<Box<Self>, Error> {
         if encver != 0 {
-            return None;
+            return Err(Error::new(format!("unknown encver {}", encver)));
         }
-        let elements = io.load_unsigned();
+        let elements = io.try_load_unsigned()?;
         let mut hto = Self::new();
         for _ in 0..elements {
-            let ele = io.load_signed();
+            let ele = io.try_load_signed()?;
             hto.push(ele);
         }
-        Some(Box::new(hto))
+        Ok(Box::new(hto))
     }
     fn rdb_save(&self, io: &mut IO) {
         let eles: Vec<&i64> = self.iter().collect();
@@ -80,10 +80,10 @@ impl TypeMethod for HelloTypeNode {
     }
     fn free(_: Box<Self>) {}
     fn aof_rewrite<T: AsRef<str>>(&self, io: &mut IO, key: T) {
+        // The elements are inserted at the head, so the oldest one is emitted first
         let eles: Vec<&i64> = self.iter().collect();
-        let keyname = key.as_ref();
-        eles.iter()
-            .for_each(|v| io.emit_aof("HELLOTYPE.INSERT", &[keyname, &v.to_string()]))
+        let mut batcher = AofBatcher::new(io, "HELLOTYPE.INSERT", &[key.as_ref()]);
+        eles.iter().rev().for_each(|v| batcher.push(*v));
     }
     /// The goal of this function is to return the amount of memory used by
     /// the HelloType value.
@@ -93,34 +93,32 @@ impl TypeMethod for HelloTypeNode {
     fn digest(&self, digest: &mut Digest) {
         let eles: Vec<&i64> = self.iter().collect();
         eles.iter().for_each(|v| digest.add_integer(**v));
-        digest.end_sequeue();
+        digest.end_sequence();
     }
 }
 
 // ========================= "hellotype" type commands =======================
 
-/// HELLOTYPE.INSERT key value
+/// HELLOTYPE.INSERT key value [value ...]
 #[rcmd("hellotype.insert", "write deny-oom", 1, 1, 1)]
 fn hellotype_insert(ctx: &mut Context, args: Vec<RStr>) -> RResult {
-    if args.len() != 3 {
+    if args.len() < 3 {
         return Err(Error::WrongArity);
     }
-    let key = ctx.open_write_key(&args[1]);
-    let exist = key.check_module_type(&HELLOTYPE)?;
-    let value = args[2]
-        .get_integer()
+    let mut key = ctx.open_write_key(&args[1]);
+    let values = args[2..]
+        .iter()
+        .map(|v| v.get_integer())
+        .collect::<Result<Vec<i64>, Error>>()
         .map_err(|_e| Error::new("ERR invalid value: must be a signed 64 bit integer"))?;
 
-    let hto: &mut HelloTypeNode = if exist {
-        key.get_value(&HELLOTYPE)?.unwrap()
-    } else {
-        let hto = HelloTypeNode::new();
-        key.set_value(&HELLOTYPE, hto)?
-    };
-    hto.push(value);
-    ctx.signal_key_as_ready(&args[1]);
-    ctx.replicate_verbatim();
-    Ok(hto.len().into())
+    let hto = key.entry(&HELLOTYPE).or_insert_with(HelloTypeNode::new)?;
+    values.into_iter().for_each(|v| hto.push(v));
+    let len = hto.len();
+    key.signal_ready();
+    drop(key);
+    ctx.replicate_verbatim()?;
+    Ok(len.into())
 }
 
 /// HELLOTYPE.RANGE key skip limit
@@ -129,7 +127,7 @@ fn hellotype_range(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     if args.len() != 4 {
         return Err(Error::WrongArity);
     }
-    let key = ctx.open_write_key(&args[1]);
+    let key = ctx.open_read_key(&args[1]);
     key.check_module_type(&HELLOTYPE)?;
     let first = args[2]
         .assert_integer(|v| v > 0)
@@ -158,7 +156,7 @@ fn hellotype_len(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     if args.len() != 2 {
         return Err(Error::WrongArity);
     }
-    let key = ctx.open_write_key(&args[1]);
+    let key = ctx.open_read_key(&args[1]);
     key.check_module_type(&HELLOTYPE)?;
     let hto = key.get_value::<HelloTypeNode>(&HELLOTYPE)?;
     if hto.is_none() {
@@ -178,8 +176,7 @@ fn hellotype_brange(ctx: &mut Context, mut args: Vec<RStr>) -> RResult {
     if args.len() != 5 {
         return Err(Error::WrongArity);
     }
-    let key = ctx.open_write_key(&args[1]);
-    let exists = key.check_module_type(&HELLOTYPE)?;
+    let exists = ctx.open_read_key(&args[1]).check_module_type(&HELLOTYPE)?;
     let timeout = args[4]
         .assert_integer(|v| v > 0)
         .map_err(|_| Error::new("ERR invalid timeout parameter"))?;
@@ -206,8 +203,7 @@ fn hellotype_brange(ctx: &mut Context, mut args: Vec<RStr>) -> RResult {
 #[rwrap("call")]
 fn helloblock_reply(ctx: &mut Context, mut args: Vec<RStr>) -> RResult {
     let keyname = ctx.get_blocked_client_ready_key().unwrap();
-    let key = ctx.open_read_key(&keyname);
-    key.check_module_type(&HELLOTYPE)?;
+    ctx.open_read_key(&keyname).check_module_type(&HELLOTYPE)?;
     args.remove(args.len() - 1);
     return hellotype_range(ctx, args);
 }
@@ -26,7 +26,7 @@ fn hello_push_native(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     if args.len() != 3 {
         return Err(Error::WrongArity);
     }
-    let key = ctx.open_write_key(&args[1]);
+    let mut key = ctx.open_write_key(&args[1]);
     key.list_push(ListPosition::Tail, &args[2])?;
     let len = key.value_length();
     Ok(len.into())
@@ -42,12 +42,7 @@ fn hello_push_call(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     if args.len() != 3 {
         return Err(Error::WrongArity);
     }
-    let call_args: Vec<&RStr> = args.iter().skip(1).collect();
-    let args = call_args
-        .iter()
-        .map(|v| v.to_str().unwrap())
-        .collect::<Vec<&str>>();
-    ctx.call("RPUSH", None, &args).unwrap().into()
+    ctx.call("RPUSH", None, &args[1..]).unwrap().into()
 }
 
 /// HELLO.PUSH.CALL2
@@ -66,13 +61,9 @@ fn hello_list_sum_len(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     if args.len() != 2 {
         return Err(Error::WrongArity);
     }
-    let call_args = [&args[1].to_str()?, "0", "-1"];
-    let reply = ctx.call("LRANGE", None, &call_args)?;
+    let reply = call!(ctx, "LRANGE", args[1], "0", "-1")?;
 
-    let elem_len = reply.get_length();
-    let str_len: usize = (0..elem_len)
-        .map(|v| reply.get_array_element(v).unwrap().get_length())
-        .sum();
+    let str_len: usize = reply.iter().map(|v| v.get_length()).sum();
     Ok(Value::from(str_len))
 }
 
@@ -85,8 +76,7 @@ fn hello_list_splice(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     if args.len() != 4 {
         return Err(Error::WrongArity);
     }
-    let src_key = ctx.open_write_key(&args[1]);
-    let dest_key = ctx.open_write_key(&args[2]);
+    let [mut src_key, mut dest_key] = ctx.open_write_keys([&args[1], &args[2]])?;
     src_key.check_type(KeyType::List)?;
     dest_key.check_type(KeyType::List)?;
     let count = args[3]
@@ -127,8 +117,8 @@ fn hello_rand_array(_ctx: &mut Context, args: Vec<RStr>) -> RResult {
 #[rcmd("hello.repl1")]
 fn hello_repl1(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
     ctx.replicate("ECHO", None, &["test:foo"])?;
-    ctx.call("INCR", Some(CallFlag::AofAndReplicas), &["test:foo"])?;
-    ctx.call("INCR", Some(CallFlag::AofAndReplicas), &["test:bar"])?;
+    ctx.call("INCR", Some(CallFlag::REPLICATE), &["test:foo"])?;
+    ctx.call("INCR", Some(CallFlag::REPLICATE), &["test:bar"])?;
     Ok(0i64.into())
 }
 
@@ -147,7 +137,7 @@ fn hello_repl2(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     if args.len() != 2 {
         return Err(Error::WrongArity);
     }
-    let key = ctx.open_write_key(&args[1]);
+    let mut key = ctx.open_write_key(&args[1]);
     let exist = key.check_type(KeyType::List)?;
     if !exist {
         return Err(Error::WrongArity);
@@ -162,7 +152,8 @@ fn hello_repl2(ctx: &mut Context, args: Vec<RStr>) -> RResult {
         let new_ele = RString::from_str(&val.to_string());
         key.list_push(ListPosition::Head, &new_ele)?;
     }
-    ctx.replicate_verbatim();
+    drop(key);
+    ctx.replicate_verbatim()?;
     Ok(sum.into())
 }
 
@@ -176,24 +167,19 @@ fn hello_toggle_case(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     if args.len() != 2 {
         return Err(Error::WrongArity);
     }
-    let key = ctx.open_write_key(&args[1]);
+    let mut key = ctx.open_write_key(&args[1]);
     key.check_type(KeyType::String)?;
     if key.get_type() == KeyType::String {
-        let value = key.string_get()?;
-        let value = value
-            .to_str()?
-            .chars()
-            .map(|v| {
-                if v.is_ascii_uppercase() {
-                    v.to_ascii_lowercase()
-                } else {
-                    v.to_ascii_uppercase()
-                }
-            })
-            .collect::<String>();
-        key.string_set(&RString::from_str(&value))?;
+        for v in key.string_dma()?.iter_mut() {
+            if v.is_ascii_uppercase() {
+                v.make_ascii_lowercase()
+            } else {
+                v.make_ascii_uppercase()
+            }
+        }
     }
-    ctx.replicate_verbatim();
+    drop(key);
+    ctx.replicate_verbatim()?;
     Ok("OK".into())
 }
 
@@ -209,12 +195,11 @@ fn hello_more_expire(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     let addms = args[2]
         .get_integer()
         .map_err(|_e| Error::new("ERR invalid expire time"))?;
-    let key = ctx.open_write_key(&args[1]);
-    let expire = key.get_expire();
+    let expire = ctx.open_read_key(&args[1]).get_expire();
     if let Some(d) = expire {
         ctx.debug(&format!("current duration {}", d.as_secs()));
         let new_d = d.checked_add(Duration::from_millis(addms as u64)).unwrap();
-        key.set_expire(new_d)?;
+        ctx.open_write_key(&args[1]).set_expire(new_d)?;
     } else {
         ctx.debug(&format!("current no duration"));
     }
@@ -302,7 +287,7 @@ fn hello_hcopy(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     if args.len() != 4 {
         return Err(Error::WrongArity);
     }
-    let key = ctx.open_write_key(&args[1]);
+    let mut key = ctx.open_write_key(&args[1]);
     key.check_type(KeyType::Hash)?;
     let old_val = key.hash_get(&args[2])?;
     if let Some(v) = &old_val {
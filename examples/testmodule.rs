@@ -1,10 +1,17 @@
 use iredismodule::call_reply::ReplyType;
-use iredismodule::io::{Digest, IO};
+use iredismodule::error::CallError;
+use iredismodule::io::{Digest, KeyOptCtx, IO};
 use iredismodule::key::KeyType;
 use iredismodule::key::{ListPosition, ZsetRangeDirection};
 use iredismodule::prelude::*;
-use iredismodule::rtype::TypeMethod;
-use iredismodule_macros::{rcmd, rtypedef};
+use iredismodule::raw;
+use iredismodule::resp::{self, Protocol};
+use iredismodule::rtype::{AuxData, AuxSaveTriggerFlag, AuxState, AuxWhen, Legacy, TypeMethod};
+use iredismodule_macros::{rcmd, rtypedef, Digestable};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::ffi::CString;
 use std::time::Duration;
 
 /// Generate RString for String or str
@@ -51,21 +58,30 @@ fn test_clear_keys(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
 
 #[rcmd("test.key")]
 fn test_key(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
-    let key_string = ctx.open_write_key(&rstr!("test:key_string"));
+    ctx.call("SADD", None, &["test:key_set", "abc", "def", "ghi"])?;
+    let key_set = ctx.open_read_key(&rstr!("test:key_set"));
+    check!(key_set.get_type() == KeyType::Set);
+    let key_nonexist = ctx.open_read_key(&rstr!("test:key_nonexist"));
+    check!(key_nonexist.check_type(KeyType::Empty).is_ok());
+    check!(key_nonexist.check_type(KeyType::String).ok() == Some(false));
+    drop((key_set, key_nonexist));
+
+    let names = [
+        rstr!("test:key_string"),
+        rstr!("test:key_list"),
+        rstr!("test:key_hash"),
+        rstr!("test:key_zset"),
+    ];
+    let [mut key_string, mut key_list, mut key_hash, mut key_zset] =
+        ctx.open_write_keys([&names[0], &names[1], &names[2], &names[3]])?;
     key_string.string_set(&rstr!("abc"))?;
     check!(key_string.get_type() == KeyType::String);
-    let key_list = ctx.open_write_key(&rstr!("test:key_list"));
     key_list.list_push(ListPosition::Head, &rstr!("abc"))?;
     key_list.list_push(ListPosition::Tail, &rstr!("def"))?;
     key_list.list_push(ListPosition::Tail, &rstr!("ghi"))?;
     check!(key_list.get_type() == KeyType::List);
-    let key_hash = ctx.open_write_key(&rstr!("test:key_hash"));
     key_hash.hash_set(None, &rstr!("field1"), Some(&rstr!("value1")))?;
     check!(key_hash.get_type() == KeyType::Hash);
-    ctx.call("SADD", None, &["test:key_set", "abc", "def", "ghi"])?;
-    let key_set = ctx.open_read_key(&rstr!("test:key_set"));
-    check!(key_set.get_type() == KeyType::Set);
-    let key_zset = ctx.open_write_key(&rstr!("test:key_zset"));
     key_zset.zset_add(0.0, &rstr!("abc"), None)?;
     key_zset.zset_add(2.0, &rstr!("ghi"), None)?;
     key_zset.zset_add(3.0, &rstr!("def"), None)?;
@@ -82,12 +98,9 @@ fn test_key(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
     let length_zset = key_zset.value_length();
     check!(length_zset == 2);
 
-    let key_nonexist = ctx.open_read_key(&rstr!("test:key_nonexist"));
     check!(key_zset.get_type() == KeyType::ZSet);
     check!(key_string.check_type(KeyType::String).is_ok());
     check!(key_string.check_type(KeyType::Hash).is_err());
-    check!(key_nonexist.check_type(KeyType::Empty).is_ok());
-    check!(key_nonexist.check_type(KeyType::String).ok() == Some(false));
 
     let value_string = key_string.string_get()?;
     check!(value_string.to_str().unwrap() == "abc");
@@ -99,19 +112,22 @@ fn test_key(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
     check!(exist_hash == true);
 
     check!(key_string.get_keyname().to_str().unwrap() == "test:key_string");
+    drop((key_string, key_list, key_hash, key_zset));
 
-    let key_expire = ctx.open_write_key(&rstr!("test:expire"));
+    let mut key_expire = ctx.open_write_key(&rstr!("test:expire"));
     key_expire.string_set(&rstr!("abc"))?;
     key_expire.set_expire(Duration::from_secs(30))?;
     let expire_ms = key_expire.get_expire().unwrap();
     check!(expire_ms.as_secs() <= 30 && expire_ms.as_secs() > 0);
+    drop(key_expire);
 
-    let key_delete = ctx.open_write_key(&rstr!("test:key_delete"));
+    let mut key_delete = ctx.open_write_key(&rstr!("test:key_delete"));
     key_delete.string_set(&rstr!("abc"))?;
     key_delete.delete()?;
     check!(key_delete.is_empty());
+    drop(key_delete);
 
-    let key_unlink = ctx.open_write_key(&rstr!("test:key_unlink"));
+    let mut key_unlink = ctx.open_write_key(&rstr!("test:key_unlink"));
     key_unlink.string_set(&rstr!("abc"))?;
     key_unlink.unlink()?;
 
@@ -153,18 +169,33 @@ fn test_reply_error(_ctx: &mut Context, _args: Vec<RStr>) -> RResult {
     Err(Error::WrongArity)
 }
 
+#[rcmd("test.reply_direct")]
+fn test_reply_direct(ctx: &mut Context, args: Vec<RStr>) -> RResult {
+    ctx.reply_array(6);
+    ctx.reply_integer(123);
+    ctx.reply_simple("abc");
+    ctx.reply_bulk([1u8, 2u8, 3u8]);
+    ctx.reply_string(&args[0]);
+    ctx.reply_null();
+    ctx.reply_postponed_array();
+    ctx.reply_double(1.5);
+    ctx.reply_error("ERR direct");
+    ctx.set_array_length(2);
+    Ok(Value::NoReply)
+}
+
 #[rcmd("test.call_reply")]
 fn test_call_reply(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
-    let call_reply_string = ctx.call("test.reply_string", None, &[])?;
+    let call_reply_string = call!(ctx, "test.reply_string")?;
     check!(call_reply_string.get_type() == ReplyType::String);
     check!(call_reply_string.get_string().unwrap() == "abc".to_string());
-    let call_reply_integer = ctx.call("test.reply_integer", None, &[])?;
+    let call_reply_integer = call!(ctx, "test.reply_integer")?;
     check!(call_reply_integer.get_type() == ReplyType::Integer);
     check!(call_reply_integer.get_integer().unwrap() == 123);
-    let call_reply_double = ctx.call("test.reply_double", None, &[])?;
+    let call_reply_double = call!(ctx, "test.reply_double")?;
     check!(call_reply_double.get_type() == ReplyType::String);
     check!(call_reply_double.get_double().unwrap() == 1.23);
-    let call_reply_bulk_string = ctx.call("test.reply_bulk_string", None, &[])?;
+    let call_reply_bulk_string = call!(ctx, "test.reply_bulk_string")?;
     check!(call_reply_bulk_string.get_type() == ReplyType::String);
     check!(call_reply_bulk_string
         .get_bulk_string()
@@ -172,7 +203,7 @@ fn test_call_reply(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
         .iter()
         .zip([1u8, 2u8, 3u8].iter())
         .all(|(x, y)| x == y));
-    let call_reply_array = ctx.call("test.reply_array", None, &[])?;
+    let call_reply_array = call!(ctx, "test.reply_array")?;
     check!(call_reply_array.get_length() == 10);
     check!(
         call_reply_array
@@ -191,10 +222,16 @@ fn test_call_reply(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
             == 9
     );
     check!(call_reply_array.get_type() == ReplyType::Array);
-    let call_reply_null = ctx.call("test.reply_null", None, &[])?;
+    let call_reply_null = call!(ctx, "test.reply_null")?;
     check!(call_reply_null.get_type() == ReplyType::Null);
-    let call_reply_error = ctx.call("test.reply_error", None, &[])?;
+    let call_reply_error = call!(ctx, "test.reply_error")?;
     check!(call_reply_error.get_type() == ReplyType::Error);
+    let call_reply_direct = call!(ctx, "test.reply_direct")?;
+    check!(
+        call_reply_direct.get_proto()
+            == b"*6\r\n:123\r\n+abc\r\n$3\r\n\x01\x02\x03\r\n$17\r\ntest.reply_direct\r\n\
+                 $-1\r\n*2\r\n$3\r\n1.5\r\n-ERR direct\r\n"
+    );
     Ok("OK".into())
 }
 
@@ -217,7 +254,7 @@ fn test_reply_value(_ctx: &mut Context, _args: Vec<RStr>) -> RResult {
 
 #[rcmd("test.value")]
 fn test_value(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
-    let call_reply = ctx.call("test.reply_value", None, &[])?;
+    let call_reply = call!(ctx, "test.reply_value")?;
     let value0: RResult = call_reply.get_array_element(0).unwrap().into();
     if let Value::String(_) = value0.unwrap() {
     } else {
@@ -251,6 +288,39 @@ fn test_value(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
     Ok("OK".into())
 }
 
+#[rcmd("test.value_from")]
+fn test_value_from(_ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    check!(Value::from(true) == Value::Integer(1));
+    check!(Value::from(-3i32) == Value::Integer(-3));
+    check!(Value::from(u32::MAX) == Value::Integer(u32::MAX as i64));
+    check!(Value::try_from(i64::MAX as u64)? == Value::Integer(i64::MAX));
+    check!(Value::try_from(u64::MAX).is_err());
+    check!(Value::from(&b"ab"[..]) == Value::BulkString(b"ab".to_vec()));
+    check!(Value::from(RString::from_str("ab")) == Value::BulkString(b"ab".to_vec()));
+    check!(
+        Value::from((1i64, "a", 1.5f64))
+            == Value::Array(vec![
+                Value::Integer(1),
+                Value::String("a".into()),
+                Value::Double(1.5)
+            ])
+    );
+    let mut map = BTreeMap::new();
+    map.insert("a", 1i64);
+    map.insert("b", 2i64);
+    check!(
+        Value::from(map) == Value::Array(vec!["a".into(), 1i64.into(), "b".into(), 2i64.into()])
+    );
+    let mut map = HashMap::new();
+    map.insert("a", 1i64);
+    check!(Value::from(map) == Value::Array(vec!["a".into(), 1i64.into()]));
+    let deque: VecDeque<i32> = (0..3).collect();
+    check!(Value::from(deque) == Value::from(vec![0i32, 1, 2]));
+    let value: Value = (0..3).map(|v| v * 2).collect();
+    check!(value == Value::from(vec![0i32, 2, 4]));
+    Ok("OK".into())
+}
+
 #[derive(Debug, PartialEq)]
 pub struct MyType {
     pub v1: u64,
@@ -262,18 +332,18 @@ pub struct MyType {
 
 #[rtypedef("mytype123", 0)]
 impl TypeMethod for MyType {
-    fn rdb_load(io: &mut IO, encver: u32) -> Option<Box<Self>> {
+    fn rdb_load(io: &mut IO, encver: u32) -> Result<Box<Self>, Error> {
         println!("mytype123 load rdb");
         if encver != 0 {
-            return None;
+            return Err(Error::new(format!("unknown encver {}", encver)));
         }
-        let v1 = io.load_unsigned();
-        let v2 = io.load_signed();
-        let v3 = io.load_string();
-        let v4 = io.load_double();
-        let v5 = io.load_float();
+        let v1 = io.try_load_unsigned()?;
+        let v2 = io.try_load_signed()?;
+        let v3 = io.try_load_string()?;
+        let v4 = io.try_load_double()?;
+        let v5 = io.try_load_float()?;
 
-        Some(Box::new(MyType { v1, v2, v3, v4, v5 }))
+        Ok(Box::new(MyType { v1, v2, v3, v4, v5 }))
     }
     fn rdb_save(&self, io: &mut IO) {
         println!("mytype123 save rdb");
@@ -297,7 +367,7 @@ impl TypeMethod for MyType {
         digest.add_string(self.v3.as_str());
         digest.add_string(self.v4.to_string());
         digest.add_string(self.v5.to_string());
-        digest.end_sequeue()
+        digest.end_sequence()
     }
     fn aof_rewrite<T: AsRef<str>>(&self, io: &mut IO, key: T) {
         println!("mytype123 aof rewrite");
@@ -315,12 +385,436 @@ impl TypeMethod for MyType {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct Blob {
+    pub data: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct BlobStats {
+    pub copies: u64,
+}
+
+impl AuxData for BlobStats {
+    fn aux_save(&self, io: &mut IO, _when: AuxWhen) {
+        io.save_unsigned(self.copies);
+    }
+    fn aux_load(io: &mut IO, encver: u32, _when: AuxWhen) -> Result<Self, Error> {
+        if encver != 0 {
+            return Err(Error::new(format!("unknown encver {}", encver)));
+        }
+        Ok(BlobStats {
+            copies: io.try_load_unsigned()?,
+        })
+    }
+}
+
+pub static BLOB_STATS: AuxState<BlobStats> = AuxState::new();
+
+#[rtypedef("testblob1", 0, aux(BLOB_STATS))]
+impl TypeMethod for Blob {
+    const AUX_SAVE_TRIGGERS: AuxSaveTriggerFlag = AuxSaveTriggerFlag::AuxAfterRdb;
+    fn rdb_load(io: &mut IO, encver: u32) -> Result<Box<Self>, Error> {
+        if encver != 0 {
+            return Err(Error::new(format!("unknown encver {}", encver)));
+        }
+        let data = io.load_bytes()?;
+        Ok(Box::new(Blob { data }))
+    }
+    fn rdb_save(&self, io: &mut IO) {
+        io.save_bytes(&self.data);
+    }
+    fn free(_: Box<Self>) {}
+    fn copy(&self, _from_key: &RStr, to_key: &RStr) -> Option<Box<Self>> {
+        if to_key.to_str().ok()? == "test:type_copy_refused" {
+            return None;
+        }
+        Some(Box::new(Blob {
+            data: self.data.clone(),
+        }))
+    }
+    fn copy2(&self, ctx: &KeyOptCtx) -> Option<Box<Self>> {
+        if ctx.get_to_keyname()?.to_str().ok()? == "test:type_copy_refused" {
+            return None;
+        }
+        Some(Box::new(Blob {
+            data: self.data.clone(),
+        }))
+    }
+    fn mem_usage2(&self, _ctx: &KeyOptCtx, _sample_size: usize) -> usize {
+        std::mem::size_of::<Self>() + self.data.len()
+    }
+    fn free_effort2(&self, _ctx: &KeyOptCtx) -> usize {
+        1
+    }
+}
+
+#[rcmd("test.type_copy", "write")]
+fn test_type_copy(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let data = vec![1u8; 1000];
+    ctx.open_write_key(&rstr!("test:type_copy"))
+        .set_value(&TESTBLOB1, Blob { data: data.clone() })?;
+    let copies = ctx.aux(&BLOB_STATS)?.copies;
+    let copied: i64 = Cmd::new("COPY")
+        .arg("test:type_copy")
+        .arg("test:type_copy_to")
+        .query(ctx)?;
+    check!(copied == 1);
+    let mut stats = ctx.aux(&BLOB_STATS)?;
+    stats.copies += 1;
+    check!(ctx.aux(&BLOB_STATS).is_err());
+    drop(stats);
+    check!(ctx.aux(&BLOB_STATS)?.copies == copies + 1);
+    let key = ctx.open_read_key(&rstr!("test:type_copy_to"));
+    let value: &Blob = key.get_value(&TESTBLOB1)?.unwrap();
+    check!(value.data == data);
+    drop(key);
+    let copied = Cmd::new("COPY")
+        .arg("test:type_copy")
+        .arg("test:type_copy_refused")
+        .query::<i64>(ctx);
+    check!(copied.is_err());
+    let usage: i64 = Cmd::new("MEMORY")
+        .arg("USAGE")
+        .arg("test:type_copy")
+        .query(ctx)?;
+    check!(usage >= data.len() as i64);
+    Ok("OK".into())
+}
+
+#[rcmd("test.io_bytes", "write")]
+fn test_io_bytes(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let data = vec![0u8, 255, b'\r', b'\n', 1];
+    ctx.open_write_key(&rstr!("test:io_bytes"))
+        .set_value(&TESTBLOB1, Blob { data: data.clone() })?;
+    let dump = call!(ctx, "DUMP", "test:io_bytes")?;
+    call!(
+        ctx,
+        "RESTORE",
+        "test:io_bytes_restored",
+        0,
+        dump.get_bytes()?
+    )?;
+    let key = ctx.open_read_key(&rstr!("test:io_bytes_restored"));
+    let value: &Blob = key.get_value(&TESTBLOB1)?.unwrap();
+    check!(value.data == data);
+    Ok("OK".into())
+}
+
+#[rcmd("test.rtype_dump", "write")]
+fn test_rtype_dump(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let blob = Blob {
+        data: vec![0u8, 1, 2, 255],
+    };
+    let data = TESTBLOB1.save(ctx, &blob)?;
+    check!(*TESTBLOB1.load(&data)? == blob);
+
+    ctx.open_write_key(&rstr!("test:rtype_dump")).set_value(
+        &TESTBLOB1,
+        Blob {
+            data: blob.data.clone(),
+        },
+    )?;
+    let key = ctx.open_read_key(&rstr!("test:rtype_dump"));
+    let data = TESTBLOB1.dump_key(ctx, &key)?.unwrap();
+    check!(key.get_expire().is_none());
+    let key_empty = ctx.open_read_key(&rstr!("test:rtype_dump_empty"));
+    check!(TESTBLOB1.dump_key(ctx, &key_empty)?.is_none());
+    drop((key, key_empty));
+
+    let mut key = ctx.open_write_key(&rstr!("test:rtype_dump_restored"));
+    check!(*TESTBLOB1.restore_key(&mut key, &data, None, false)? == blob);
+    check!(key.get_expire().is_none());
+    check!(TESTBLOB1.restore_key(&mut key, &data, None, false).is_err());
+    let expire = Some(Duration::from_secs(30));
+    check!(*TESTBLOB1.restore_key(&mut key, &data, expire, true)? == blob);
+    check!(key.get_expire().is_some());
+    Ok("OK".into())
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Digestable)]
+pub enum SerdeKind {
+    Empty,
+    Ratio(f32),
+    Range { start: i64, end: i64 },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Digestable)]
+pub struct SerdeValue {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub members: HashSet<String>,
+    #[digest(skip)]
+    pub hits: u64,
+    pub counts: BTreeMap<String, u64>,
+    pub score: f64,
+    pub ttl: Option<i32>,
+    pub kinds: Vec<SerdeKind>,
+}
+
+#[rtypedef("testserde", 0, serde, digest)]
+impl TypeMethod for SerdeValue {
+    fn free(_: Box<Self>) {}
+}
+
+#[rcmd("test.serde", "write")]
+fn test_serde(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let mut counts = BTreeMap::new();
+    counts.insert("a".to_owned(), 1);
+    counts.insert("b\r\n".to_owned(), u64::MAX);
+    let make_value = || SerdeValue {
+        name: "hello\0world".to_owned(),
+        tags: vec!["x".to_owned(), "".to_owned()],
+        members: ["m1", "m2"].iter().map(|v| v.to_string()).collect(),
+        hits: 3,
+        counts: counts.clone(),
+        score: -1.5,
+        ttl: None,
+        kinds: vec![
+            SerdeKind::Empty,
+            SerdeKind::Ratio(0.25),
+            SerdeKind::Range {
+                start: i64::MIN,
+                end: 3,
+            },
+        ],
+    };
+    ctx.open_write_key(&rstr!("test:serde"))
+        .set_value(&TESTSERDE, make_value())?;
+    let dump = call!(ctx, "DUMP", "test:serde")?;
+    call!(ctx, "RESTORE", "test:serde_restored", 0, dump.get_bytes()?)?;
+    let key = ctx.open_read_key(&rstr!("test:serde_restored"));
+    let value: &SerdeValue = key.get_value(&TESTSERDE)?.unwrap();
+    check!(value == &make_value());
+    Ok("OK".into())
+}
+
+#[rcmd("test.digest", "write")]
+fn test_digest(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let make_value = |members: &[&str], hits: u64| SerdeValue {
+        name: "digest".to_owned(),
+        tags: vec![],
+        members: members.iter().map(|v| v.to_string()).collect(),
+        hits,
+        counts: BTreeMap::new(),
+        score: 0.5,
+        ttl: Some(1),
+        kinds: vec![SerdeKind::Ratio(0.5)],
+    };
+    let members: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+    let members: Vec<&str> = members.iter().map(|v| v.as_str()).collect();
+    let reversed: Vec<&str> = members.iter().rev().cloned().collect();
+    ctx.open_write_key(&rstr!("test:digest1"))
+        .set_value(&TESTSERDE, make_value(&members, 1))?;
+    ctx.open_write_key(&rstr!("test:digest2"))
+        .set_value(&TESTSERDE, make_value(&reversed, 2))?;
+    ctx.open_write_key(&rstr!("test:digest3"))
+        .set_value(&TESTSERDE, make_value(&members[1..], 1))?;
+    let reply = call!(
+        ctx,
+        "DEBUG",
+        "DIGEST-VALUE",
+        "test:digest1",
+        "test:digest2",
+        "test:digest3"
+    )?;
+    if reply.get_type() == ReplyType::Error {
+        // DEBUG is disabled by "enable-debug-command"
+        return Ok("OK".into());
+    }
+    let digests: Vec<String> = reply.parse()?;
+    check!(digests[0] == digests[1]);
+    check!(digests[0] != digests[2]);
+    Ok("OK".into())
+}
+
+pub struct MigrateV0 {
+    pub count: i64,
+}
+
+impl Legacy for MigrateV0 {
+    const ENCVER: u32 = 0;
+    fn rdb_load(io: &mut IO) -> Result<Self, Error> {
+        Ok(MigrateV0 {
+            count: io.try_load_signed()?,
+        })
+    }
+}
+
+pub struct MigrateV1 {
+    pub count: i64,
+    pub label: String,
+}
+
+impl Legacy for MigrateV1 {
+    const ENCVER: u32 = 1;
+    fn rdb_load(io: &mut IO) -> Result<Self, Error> {
+        let count = io.try_load_signed()?;
+        let label = io.try_load_string()?;
+        Ok(MigrateV1 { count, label })
+    }
+}
+
+impl From<MigrateV0> for MigrateV1 {
+    fn from(v: MigrateV0) -> Self {
+        MigrateV1 {
+            count: v.count,
+            label: "v0".to_owned(),
+        }
+    }
+}
+
+impl From<MigrateV1> for Migrate {
+    fn from(v: MigrateV1) -> Self {
+        Migrate {
+            label: v.label,
+            count: v.count,
+            scale: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migrate {
+    pub label: String,
+    pub count: i64,
+    pub scale: f64,
+}
+
+#[rtypedef("testmigr1", 2, migrate(MigrateV0, MigrateV1))]
+impl TypeMethod for Migrate {
+    fn rdb_load(io: &mut IO, encver: u32) -> Result<Box<Self>, Error> {
+        if encver != 2 {
+            return Err(Error::new(format!("unknown encver {}", encver)));
+        }
+        let label = io.try_load_string()?;
+        let count = io.try_load_signed()?;
+        let scale = io.try_load_double()?;
+        Ok(Box::new(Migrate {
+            label,
+            count,
+            scale,
+        }))
+    }
+    fn rdb_save(&self, io: &mut IO) {
+        io.save_string(&self.label);
+        io.save_signed(self.count);
+        io.save_double(self.scale);
+    }
+    fn free(_: Box<Self>) {}
+}
+
+/// Build the DUMP payload of a module type value, as saved by the `encver` of the type
+fn module_dump_payload(name: &str, encver: u64, body: &[u8]) -> Vec<u8> {
+    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut id = 0u64;
+    for c in name.bytes() {
+        id = (id << 6) | CHARSET.iter().position(|v| *v == c).unwrap() as u64;
+    }
+    id = (id << 10) | encver;
+    // RDB_TYPE_MODULE_2, then the module id as 64 bits length
+    let mut payload = vec![7, 0x81];
+    payload.extend_from_slice(&id.to_be_bytes());
+    payload.extend_from_slice(body);
+    // RDB_MODULE_OPCODE_EOF, RDB version 9
+    payload.extend_from_slice(&[0, 9, 0]);
+    // CRC64 Jones, as Redis does
+    let mut crc = 0u64;
+    for c in payload.iter() {
+        crc ^= *c as u64;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x95ac_9329_ac4b_c9b5
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    payload.extend_from_slice(&crc.to_le_bytes());
+    payload
+}
+
+/// Encode a signed integer and a string like `IO::save_signed` and `IO::save_string` do
+fn module_dump_body(count: i64, label: Option<&str>) -> Vec<u8> {
+    // RDB_MODULE_OPCODE_SINT
+    let mut body = vec![1, 0x81];
+    body.extend_from_slice(&(count as u64).to_be_bytes());
+    if let Some(label) = label {
+        // RDB_MODULE_OPCODE_STRING
+        body.extend_from_slice(&[5, 0x81]);
+        body.extend_from_slice(&(label.len() as u64).to_be_bytes());
+        body.extend_from_slice(label.as_bytes());
+    }
+    body
+}
+
+#[rcmd("test.migrate", "write")]
+fn test_migrate(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let restore =
+        |ctx: &mut Context, key: &str, payload: &[u8]| -> Result<Option<Migrate>, Error> {
+            call!(ctx, "RESTORE", key, 0, payload)?.parse::<()>()?;
+            let key = ctx.open_read_key(&rstr!(key));
+            let value: Option<&Migrate> = key.get_value(&TESTMIGR1)?;
+            Ok(value.cloned())
+        };
+    let current = Migrate {
+        label: "v2".to_owned(),
+        count: 2,
+        scale: 0.5,
+    };
+    ctx.open_write_key(&rstr!("test:migrate"))
+        .set_value(&TESTMIGR1, current)?;
+    let dump = call!(ctx, "DUMP", "test:migrate")?;
+    let value = restore(ctx, "test:migrate_v2", dump.get_bytes()?)?.unwrap();
+    check!(value.label == "v2" && value.count == 2 && value.scale == 0.5);
+
+    let payload = module_dump_payload("testmigr1", 0, &module_dump_body(-7, None));
+    let value = restore(ctx, "test:migrate_v0", &payload)?.unwrap();
+    check!(value.label == "v0" && value.count == -7 && value.scale == 1.0);
+
+    let payload = module_dump_payload("testmigr1", 1, &module_dump_body(7, Some("v1")));
+    let value = restore(ctx, "test:migrate_v1", &payload)?.unwrap();
+    check!(value.label == "v1" && value.count == 7 && value.scale == 1.0);
+
+    let payload = module_dump_payload("testmigr1", 3, &module_dump_body(7, Some("v1")));
+    check!(restore(ctx, "test:migrate_v3", &payload).is_err());
+    Ok("OK".into())
+}
+
+pub mod wrapped {
+    #[derive(Debug, PartialEq)]
+    pub struct Counter<T> {
+        pub value: T,
+    }
+}
+
+#[rtypedef("testwrap1", 0, static_name = "TEST_WRAPPED_COUNTER")]
+impl TypeMethod for wrapped::Counter<i64> {
+    fn rdb_load(io: &mut IO, _encver: u32) -> Result<Box<Self>, Error> {
+        Ok(Box::new(wrapped::Counter {
+            value: io.try_load_signed()?,
+        }))
+    }
+    fn rdb_save(&self, io: &mut IO) {
+        io.save_signed(self.value);
+    }
+    fn free(_: Box<Self>) {}
+}
+
+#[rcmd("test.rtype_path", "write")]
+fn test_rtype_path(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let counter = wrapped::Counter { value: -3i64 };
+    let data = TEST_WRAPPED_COUNTER.save(ctx, &counter)?;
+    check!(*TEST_WRAPPED_COUNTER.load(&data)? == counter);
+    Ok("OK".into())
+}
+
 #[rcmd("test.set_type", "write deny-oom", 1, 1, 1)]
 fn test_set_type(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     if args.len() != 7 {
         return Err(Error::WrongArity);
     }
-    let key = ctx.open_write_key(&args[1]);
+    let mut key = ctx.open_write_key(&args[1]);
     key.check_module_type(&MYTYPE123)?;
     let value = MyType {
         v1: args[2].get_integer()? as u64,
@@ -338,7 +832,7 @@ fn test_get_type(ctx: &mut Context, args: Vec<RStr>) -> RResult {
     let key = ctx.open_read_key(&args[1]);
     check!(key.get_type() == KeyType::Module);
     let exist = key.check_module_type(&MYTYPE123)?;
-    let value: &mut MyType = key.get_value(&MYTYPE123)?.unwrap();
+    let value: &MyType = key.get_value(&MYTYPE123)?.unwrap();
     check!(exist);
     Ok(Value::Array(vec![
         (value.v1 as i64).into(),
@@ -365,6 +859,65 @@ fn test_type(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
     Ok("OK".into())
 }
 
+#[rcmd("test.entry", "write")]
+fn test_entry(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let new_value = || MyType {
+        v1: 1,
+        v2: -1,
+        v3: "abc".to_owned(),
+        v4: 1.23,
+        v5: 3.21,
+    };
+    let name = rstr!("test:entry");
+    check!(ctx.open_write_keys([&name, &name]).is_err());
+    let mut key = ctx.open_write_key(&name);
+    check!(key.entry(&MYTYPE123).occupied()?.is_none());
+    let value = key.entry(&MYTYPE123).or_insert_with(new_value)?;
+    check!(value.v1 == 1);
+    let value = key
+        .entry(&MYTYPE123)
+        .and_modify(|v| v.v1 += 1)
+        .or_insert_with(new_value)?;
+    check!(value.v1 == 2);
+    let entry = key.entry(&MYTYPE123).occupied()?.unwrap();
+    check!(!entry.remove_if(|v| v.v1 == 0)?);
+    let entry = key.entry(&MYTYPE123).occupied()?.unwrap();
+    check!(entry.remove_if(|v| v.v1 == 2)?);
+    check!(key.is_empty());
+    drop(key);
+
+    let mut key_string = ctx.open_write_key(&rstr!("test:entry_string"));
+    key_string.string_set(&rstr!("abc"))?;
+    check!(key_string
+        .entry(&MYTYPE123)
+        .or_insert_with(new_value)
+        .is_err());
+    Ok("OK".into())
+}
+
+#[rcmd("test.key_modified", "write")]
+fn test_key_modified(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let mut key = ctx.open_write_key(&rstr!("test:key_modified"));
+    key.set_notify_event(raw::REDISMODULE_NOTIFY_GENERIC as i32, "test.key_modified");
+    check!(!key.is_modified());
+    key.string_set(&rstr!("abc"))?;
+    check!(key.is_modified());
+    key.commit()?;
+    check!(!key.is_modified());
+    key.string_dma()?.make_ascii_uppercase();
+    check!(key.is_modified());
+    check!(key.string_get()?.to_str()? == "ABC");
+    key.delete()?;
+    key.set_value(&TESTBLOB1, Blob { data: vec![1] })?;
+    key.commit()?;
+    check!(key.get_value(&TESTBLOB1)?.unwrap().data == [1]);
+    check!(!key.is_modified());
+    key.get_value_mut(&TESTBLOB1)?.unwrap().data.push(2);
+    check!(key.is_modified());
+    key.delete()?;
+    Ok("OK".into())
+}
+
 #[rcmd("test.misc")]
 fn test_misc(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
     ctx.select_db(1)?;
@@ -380,15 +933,359 @@ fn test_misc(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
     Ok("OK".into())
 }
 
+#[rcmd("test.db", "write")]
+fn test_db(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    {
+        let ctx = ctx.select_db_scoped(1)?;
+        check!(ctx.get_select_db() == 1);
+    }
+    check!(ctx.get_select_db() == 0);
+    let result: Result<(), Error> = ctx.with_db(1, |_| Err(Error::new("fail")));
+    check!(result.is_err());
+    check!(ctx.get_select_db() == 0);
+
+    let mut key = ctx.open_write_key_in_db(1, &rstr!("test:db"))?;
+    key.string_set(&rstr!("abc"))?;
+    drop(key);
+    check!(ctx.get_select_db() == 0);
+    check!(ctx.open_read_key(&rstr!("test:db")).is_empty());
+    let value = ctx.with_db(1, |ctx| ctx.open_read_key(&rstr!("test:db")).string_get())?;
+    check!(value.to_str()? == "abc");
+    ctx.open_write_key_in_db(1, &rstr!("test:db"))?.delete()?;
+    Ok("OK".into())
+}
+
+#[rcmd("test.keyspace", "write")]
+fn test_keyspace(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let mut key = ctx.open_write_key(&rstr!("test:keyspace_string"));
+    key.string_set(&rstr!("abc"))?;
+    key.set_expire(Duration::from_secs(30))?;
+    drop(key);
+    let mut key = ctx.open_write_key(&rstr!("test:keyspace_type"));
+    key.set_value(
+        &TESTBLOB1,
+        Blob {
+            data: b"abc".to_vec(),
+        },
+    )?;
+    key.set_expire(Duration::from_secs(30))?;
+    drop(key);
+
+    check!(ctx.random_key().is_some());
+    check!(ctx.key_exists(&rstr!("test:keyspace_string")));
+    check!(!ctx.key_exists(&rstr!("test:keyspace_none")));
+
+    for name in &["test:keyspace_string", "test:keyspace_type"] {
+        let src = rstr!(name);
+        let copy = rstr!(format!("{}_copy", name));
+        let renamed = rstr!(format!("{}_renamed", name));
+        check!(ctx.copy_key(&src, &copy, false)?);
+        check!(!ctx.copy_key(&src, &copy, false)?);
+        check!(ctx.copy_key(&src, &copy, true)?);
+        check!(ctx.open_read_key(&copy).get_expire().is_some());
+        ctx.rename_key(&copy, &renamed)?;
+        check!(!ctx.key_exists(&copy));
+        check!(ctx.open_read_key(&renamed).get_expire().is_some());
+    }
+    check!(ctx
+        .rename_key(&rstr!("test:keyspace_none"), &rstr!("test:a"))
+        .is_err());
+    let key = ctx.open_read_key(&rstr!("test:keyspace_string_renamed"));
+    check!(key.string_get()?.to_str()? == "abc");
+    drop(key);
+    let key = ctx.open_read_key(&rstr!("test:keyspace_type_renamed"));
+    check!(key.get_value(&TESTBLOB1)?.unwrap().data == b"abc");
+    drop(key);
+    // MyType has no copy method
+    let mut key = ctx.open_write_key(&rstr!("test:keyspace_nocopy"));
+    key.set_value(
+        &MYTYPE123,
+        MyType {
+            v1: 1,
+            v2: -1,
+            v3: "abc".to_owned(),
+            v4: 1.23,
+            v5: 3.21,
+        },
+    )?;
+    drop(key);
+    check!(ctx
+        .copy_key(
+            &rstr!("test:keyspace_nocopy"),
+            &rstr!("test:keyspace_nocopy_copy"),
+            false
+        )
+        .is_err());
+    Ok("OK".into())
+}
+
+#[rcmd("test.call_arg", "write")]
+fn test_call_arg(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let key = rstr!("test:call_arg");
+    call!(ctx, "SET", key, &b"a\x00b"[..], "EX", 10)?;
+    let reply = call!(ctx, "GET", key)?;
+    check!(reply.get_bulk_string()? == b"a\x00b");
+    let reply = call!(ctx, "INCRBYFLOAT", "test:call_arg_float", 1.5)?;
+    check!(reply.get_string()? == "1.5");
+    let args: [&dyn ToCallArg; 2] = [&"test:call_arg_int", &2u64];
+    let reply = ctx.call("INCRBY", None, &args)?;
+    check!(reply.get_integer()? == 2);
+    let reply = ctx.call("DEL", None, &[key.to_string().into_bytes()])?;
+    check!(reply.get_integer()? == 1);
+    Ok("OK".into())
+}
+
+#[rcmd("test.call_flag")]
+fn test_call_flag(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let fmt: CString = (CallFlag::REPLICATE | CallFlag::NO_AOF).into();
+    check!(fmt.as_bytes() == b"v!A");
+    let flags = CallFlag::REPLICATE | CallFlag::CHECK_ACL;
+    check!(flags.contains(CallFlag::CHECK_ACL));
+    check!(!flags.contains(CallFlag::NO_AOF));
+    check!(flags.min_version() == 0x00_07_00_00);
+    let version = ctx.get_server_version()?;
+    check!(version >= 0x00_06_00_00);
+    let reply = ctx.call("ECHO", Some(CallFlag::CHECK_ACL), &["abc"]);
+    check!(reply.is_ok() == (version >= 0x00_07_00_00));
+    Ok("OK".into())
+}
+
+#[rcmd("test.call_error")]
+fn test_call_error(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    match call!(ctx, "test.nonexistent") {
+        Err(Error::Call { error, .. }) => check!(error == CallError::UnknownCommand),
+        _ => check!(false, "call unknown command"),
+    }
+    match call!(ctx, "GET") {
+        Err(err) => check!(err.to_string() == "ERR wrong number of arguments for 'GET' command"),
+        _ => check!(false, "call with wrong arity"),
+    }
+    Ok("OK".into())
+}
+
+#[rcmd("test.call_reply_nav", "write")]
+fn test_call_reply_nav(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    call!(ctx, "RPUSH", "test:call_reply_nav_list", "a", "bc", "def")?;
+    let reply = call!(ctx, "LRANGE", "test:call_reply_nav_list", 0, -1)?;
+    check!(reply[1].get_bytes()? == b"bc");
+    let lens: Vec<usize> = reply.iter().map(|v| v.get_length()).collect();
+    check!(lens == vec![1, 2, 3]);
+
+    if ctx.get_server_version()? < 0x00_07_00_00 {
+        return Ok("OK".into());
+    }
+    call!(
+        ctx,
+        "HSET",
+        "test:call_reply_nav_hash",
+        "f1",
+        "v1",
+        "f2",
+        "v2"
+    )?;
+    let reply = ctx.call(
+        "HGETALL",
+        Some(CallFlag::RESP3),
+        &["test:call_reply_nav_hash"],
+    )?;
+    check!(reply.get_type() == ReplyType::Map);
+    let pairs: Vec<(&str, &str)> = reply
+        .map_iter()
+        .map(|(k, v)| (k.get_str().unwrap(), v.get_str().unwrap()))
+        .collect();
+    check!(pairs.contains(&("f2", "v2")));
+    call!(ctx, "SADD", "test:call_reply_nav_set", "a")?;
+    let reply = ctx.call(
+        "SMEMBERS",
+        Some(CallFlag::RESP3),
+        &["test:call_reply_nav_set"],
+    )?;
+    check!(reply.get_type() == ReplyType::Set);
+    check!(reply[0].get_str()? == "a");
+    call!(ctx, "ZADD", "test:call_reply_nav_zset", 1.5, "a")?;
+    let reply = ctx.call(
+        "ZSCORE",
+        Some(CallFlag::RESP3),
+        &["test:call_reply_nav_zset", "a"],
+    )?;
+    check!(reply.get_type() == ReplyType::Double);
+    check!(reply.get_double()? == 1.5);
+    Ok("OK".into())
+}
+
+#[rcmd("test.cmd", "write")]
+fn test_cmd(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    let key = "test:cmd_zset";
+    let pairs = ["1", "a", "2", "b"];
+    let added: i64 = Cmd::new("ZADD").arg(key).args(&pairs).query(ctx)?;
+    check!(added == 2);
+    let added: i64 = Cmd::new("ZADD")
+        .arg(key)
+        .arg_if(true, "NX")
+        .arg(&3)
+        .arg("a")
+        .query(ctx)?;
+    check!(added == 0);
+    let score: f64 = Cmd::new("ZSCORE").arg(key).arg("a").query(ctx)?;
+    check!(score == 1.0);
+    let members: Vec<String> = Cmd::new("ZRANGE")
+        .arg(key)
+        .arg(&0)
+        .arg(&-1)
+        .arg_if(false, "WITHSCORES")
+        .query(ctx)?;
+    check!(members == vec!["a".to_owned(), "b".to_owned()]);
+    let missing: Option<String> = Cmd::new("GET").arg("test:cmd_missing").query(ctx)?;
+    check!(missing.is_none());
+    match Cmd::new("ZADD").arg(key).arg("x").query::<i64>(ctx) {
+        Err(err) => check!(err.to_string().starts_with("ERR")),
+        _ => check!(false, "query error reply"),
+    }
+    let reply = Cmd::new("ZCARD").arg(key).exec(ctx)?;
+    check!(reply.get_integer()? == 2);
+    Cmd::new("SET")
+        .arg("test:cmd_replicated")
+        .arg("1")
+        .replicate(ctx)?;
+    Ok("OK".into())
+}
+
+#[rcmd("test.resp", "write")]
+fn test_resp(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    call!(ctx, "RPUSH", "test:resp_list", "a", "", "c\r\nd")?;
+    let replies = [
+        call!(ctx, "SET", "test:resp_string", "1")?,
+        call!(ctx, "INCR", "test:resp_string")?,
+        call!(ctx, "GET", "test:resp_missing")?,
+        call!(ctx, "LRANGE", "test:resp_list", 0, -1)?,
+    ];
+    for reply in replies.iter() {
+        let value = reply.to_value_via_proto()?;
+        check!(value == RResult::from(&**reply)?);
+        check!(value.to_resp_bytes(Protocol::Resp2) == reply.get_proto());
+    }
+    let value = Value::Array(vec![Value::Double(1.5), Value::Null]);
+    check!(value.to_resp_bytes(Protocol::Resp2) == b"*2\r\n$3\r\n1.5\r\n$-1\r\n");
+    check!(value.to_resp_bytes(Protocol::Resp3) == b"*2\r\n,1.5\r\n_\r\n");
+    check!(resp::decode(&value.to_resp_bytes(Protocol::Resp3))? == value);
+    check!(resp::decode(b"|1\r\n+a\r\n:1\r\n#t\r\n")? == Value::Integer(1));
+    check!(resp::decode(b"$3\r\nab").is_err());
+    for bogus in [
+        &b"*-5\r\n"[..],
+        b"*99999999999\r\n",
+        b"%9223372036854775807\r\n",
+        b"$-2\r\n",
+        b"$18446744073709551615\r\n",
+    ] {
+        check!(resp::decode(bogus).is_err());
+    }
+    match resp::decode(b"-ERR oops\r\n") {
+        Err(err) => check!(err.to_string() == "ERR oops"),
+        _ => check!(false, "decode error reply"),
+    }
+    let (value, len) = resp::decode_prefix(b":1\r\n:2\r\n")?;
+    check!(value? == Value::Integer(1) && len == 4);
+    let value = Value::BigNumber("1234567999999999999999999999".to_owned());
+    check!(value.to_resp_bytes(Protocol::Resp2) == b"$28\r\n1234567999999999999999999999\r\n");
+    check!(value.to_resp_bytes(Protocol::Resp3) == b"(1234567999999999999999999999\r\n");
+    check!(resp::decode(&value.to_resp_bytes(Protocol::Resp3))? == value);
+    let nested = format!("{}:1\r\n", "*1\r\n".repeat(1000));
+    check!(resp::decode(nested.as_bytes()).is_err());
+    let attrs = format!("{}:1\r\n", "|0\r\n".repeat(100_000));
+    check!(resp::decode(attrs.as_bytes())? == Value::Integer(1));
+    let value = Value::Array(vec![
+        Value::String("a\r\nb".to_owned()),
+        Value::Error("ERR a\r\nb".to_owned()),
+        Value::Double(0.1),
+        Value::Double(1e-5),
+        Value::Double(1e21),
+    ]);
+    check!(
+        value.to_resp_bytes(Protocol::Resp3)
+            == b"*5\r\n$4\r\na\r\nb\r\n-ERR a  b\r\n,0.1\r\n,1e-05\r\n,1e+21\r\n"
+    );
+
+    if ctx.get_server_version()? < 0x00_07_00_00 {
+        return Ok("OK".into());
+    }
+    call!(ctx, "HSET", "test:resp_hash", "f1", "v1")?;
+    let reply = ctx.call("HGETALL", Some(CallFlag::RESP3), &["test:resp_hash"])?;
+    check!(reply.to_value_via_proto()? == RResult::from(reply)?);
+    Ok("OK".into())
+}
+
+/// TEST.REPLY_CONVERTED command arg ... -- call the command and reply with
+/// the reply converted to a value
+#[rcmd("test.reply_converted", "write")]
+fn test_reply_converted(ctx: &mut Context, args: Vec<RStr>) -> RResult {
+    if args.len() < 2 {
+        return Err(Error::WrongArity);
+    }
+    let reply = ctx.call(args[1].to_str()?, None, &args[2..])?;
+    reply.into()
+}
+
+#[rcmd("test.call_reply_proto", "write")]
+fn test_call_reply_proto(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    call!(ctx, "SET", "test:call_reply_proto_empty", "")?;
+    call!(ctx, "RPUSH", "test:call_reply_proto_list", "a", "b")?;
+    let cases: Vec<Vec<&str>> = vec![
+        vec!["SET", "test:call_reply_proto_string", "1"],
+        vec!["GET", "test:call_reply_proto_missing"],
+        vec!["GET", "test:call_reply_proto_empty"],
+        vec!["INCR", "test:call_reply_proto_list"],
+        vec!["LRANGE", "test:call_reply_proto_list", "0", "-1"],
+        vec!["LRANGE", "test:call_reply_proto_missing", "0", "-1"],
+        vec!["BLPOP", "test:call_reply_proto_missing", "1"],
+        vec![
+            "EVAL",
+            "return {1, false, {}, 'a', redis.error_reply('ERR nested')}",
+            "0",
+        ],
+        vec![
+            "EVAL",
+            "return {{ok='OK'}, redis.status_reply('PONG')}",
+            "0",
+        ],
+    ];
+    for case in cases {
+        let direct = ctx.call(case[0], None, &case[1..])?;
+        let converted = ctx.call("test.reply_converted", None, &case)?;
+        check!(
+            direct.get_proto() == converted.get_proto(),
+            format!("reply of {} is not the same", case[0])
+        );
+        let encoded = resp::encode_result(&RResult::from(&*direct), Protocol::Resp2);
+        check!(encoded == direct.get_proto());
+        check!(direct.to_value_via_proto().ok() == RResult::from(&*direct).ok());
+    }
+    let reply = call!(ctx, "BLPOP", "test:call_reply_proto_missing", 1)?;
+    check!(Value::from(reply) == Value::NullArray);
+    Ok("OK".into())
+}
+
+#[rcmd("test.replicate_effects", "write random", replicate_effects)]
+fn test_replicate_effects(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
+    check!(ctx.is_replicate_effects());
+    let now = call!(ctx, "TIME")?;
+    call!(ctx, "SET", "test:replicate_effects", now[0].get_bytes()?)?;
+    ctx.replicate("SET", None, &["test:replicate_effects_explicit", "1"])?;
+    check!(ctx.replicate_verbatim().is_err());
+    ctx.set_replicate_effects(false);
+    check!(!ctx.is_replicate_effects());
+    ctx.set_replicate_effects(true);
+    Ok("OK".into())
+}
+
 #[rcmd("test.example_simple")]
 fn test_example_simple(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
-    ctx.call("hello.simple", None, &[])?;
+    call!(ctx, "hello.simple")?;
     Ok("OK".into())
 }
 
 #[rcmd("test.example_helloworld")]
 fn test_example_helloworld(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
-    let reply = ctx.call("hello.simple", None, &[])?;
+    let reply = call!(ctx, "hello.simple")?;
     check!(reply.get_type() == ReplyType::Integer);
     let reply = ctx.call("hello.push.native", None, &["test:helloword:key1", "1"])?;
     check!(reply.get_type() == ReplyType::Integer);
@@ -406,7 +1303,7 @@ fn test_example_helloworld(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
     check!(reply.get_type() == ReplyType::Integer);
     let reply = ctx.call("hello.rand.array", None, &["5"])?;
     check!(reply.get_type() == ReplyType::Array);
-    let reply = ctx.call("hello.repl1", None, &[])?;
+    let reply = call!(ctx, "hello.repl1")?;
     check!(reply.get_type() == ReplyType::Integer);
     let reply = ctx.call("hello.repl2", None, &["test:helloword:key2"])?;
     check!(reply.get_type() == ReplyType::Integer);
@@ -454,6 +1351,10 @@ fn test_example_hellotype(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
     ctx.call("hellotype.insert", None, &["test:hellotype:key1", "2"])?;
     ctx.call("hellotype.insert", None, &["test:hellotype:key1", "3"])?;
     ctx.call("hellotype.insert", None, &["test:hellotype:key1", "4"])?;
+    let len: i64 = call!(ctx, "hellotype.insert", "test:hellotype:key1", 5, 6, 7)?.parse()?;
+    check!(len == 7);
+    let reply = call!(ctx, "hellotype.insert", "test:hellotype:key1", 8, "x")?;
+    check!(reply.get_type() == ReplyType::Error);
     let reply = ctx.call("hellotype.range", None, &["test:hellotype:key1", "1", "2"])?;
     check!(reply.get_type() == ReplyType::Array);
     let reply = ctx.call("hellotype.len", None, &["test:hellotype:key1"])?;
@@ -468,7 +1369,7 @@ fn test_example_hellotype(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
 
 #[rcmd("test.example_hellotimer")]
 fn test_example_hellotimer(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
-    let reply = ctx.call("hellotimer.timer", None, &[])?;
+    let reply = call!(ctx, "hellotimer.timer")?;
     check!(reply.get_type() == ReplyType::String);
     Ok("OK".into())
 }
@@ -483,27 +1384,67 @@ fn test_example_helloblock(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
 
 #[rcmd("test.all")]
 fn test_all(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
-    let reply = ctx.call("test.clear_keys", None, &[])?;
+    let reply = call!(ctx, "test.clear_keys")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.key")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.call_reply")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.value")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.value_from")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.type")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.io_bytes")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.rtype_dump")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.rtype_path")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.type_copy")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.serde")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.digest")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.migrate")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.entry")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.key_modified")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.db")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.keyspace")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.call_arg")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.call_flag")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.call_error")?;
+    check!(reply.get_type() == ReplyType::String);
+    let reply = call!(ctx, "test.call_reply_nav")?;
     check!(reply.get_type() == ReplyType::String);
-    let reply = ctx.call("test.key", None, &[])?;
+    let reply = call!(ctx, "test.cmd")?;
     check!(reply.get_type() == ReplyType::String);
-    let reply = ctx.call("test.call_reply", None, &[])?;
+    let reply = call!(ctx, "test.resp")?;
     check!(reply.get_type() == ReplyType::String);
-    let reply = ctx.call("test.value", None, &[])?;
+    let reply = call!(ctx, "test.call_reply_proto")?;
     check!(reply.get_type() == ReplyType::String);
-    let reply = ctx.call("test.type", None, &[])?;
+    let reply = call!(ctx, "test.replicate_effects")?;
     check!(reply.get_type() == ReplyType::String);
-    let reply = ctx.call("test.misc", None, &[])?;
+    let reply = call!(ctx, "test.misc")?;
     check!(reply.get_type() == ReplyType::String);
-    let reply = ctx.call("test.example_simple", None, &[])?;
+    let reply = call!(ctx, "test.example_simple")?;
     check!(reply.get_type() == ReplyType::String);
-    let reply = ctx.call("test.example_helloworld", None, &[])?;
+    let reply = call!(ctx, "test.example_helloworld")?;
     check!(reply.get_type() == ReplyType::String);
-    let reply = ctx.call("test.example_hellotype", None, &[])?;
+    let reply = call!(ctx, "test.example_hellotype")?;
     check!(reply.get_type() == ReplyType::String);
-    let reply = ctx.call("test.example_hellotimer", None, &[])?;
+    let reply = call!(ctx, "test.example_hellotimer")?;
     check!(reply.get_type() == ReplyType::String);
-    let reply = ctx.call("test.example_helloblock", None, &[])?;
+    let reply = call!(ctx, "test.example_helloblock")?;
     check!(reply.get_type() == ReplyType::String);
     Ok("OK".into())
 }
@@ -513,6 +1454,10 @@ define_module! {
     version: 1,
     data_types: [
         MYTYPE123,
+        TESTBLOB1,
+        TESTSERDE,
+        TESTMIGR1,
+        TEST_WRAPPED_COUNTER,
     ],
     init_funcs: [],
     commands: [
@@ -525,12 +1470,34 @@ define_module! {
         test_reply_array_cmd,
         test_reply_null_cmd,
         test_reply_error_cmd,
+        test_reply_direct_cmd,
         test_reply_value_cmd,
         test_call_reply_cmd,
         test_value_cmd,
+        test_value_from_cmd,
         test_set_type_cmd,
         test_get_type_cmd,
         test_type_cmd,
+        test_io_bytes_cmd,
+        test_rtype_dump_cmd,
+        test_rtype_path_cmd,
+        test_type_copy_cmd,
+        test_serde_cmd,
+        test_digest_cmd,
+        test_migrate_cmd,
+        test_entry_cmd,
+        test_key_modified_cmd,
+        test_db_cmd,
+        test_keyspace_cmd,
+        test_call_arg_cmd,
+        test_call_flag_cmd,
+        test_call_error_cmd,
+        test_call_reply_nav_cmd,
+        test_cmd_cmd,
+        test_resp_cmd,
+        test_reply_converted_cmd,
+        test_call_reply_proto_cmd,
+        test_replicate_effects_cmd,
         test_misc_cmd,
         test_example_simple_cmd,
         test_example_helloworld_cmd,
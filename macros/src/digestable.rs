@@ -0,0 +1,133 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Index};
+
+pub fn derive_digestable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param
+            .bounds
+            .push(syn::parse_quote!(iredismodule::io::Digestable));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match digest_body(&input) {
+        Ok(body) => body,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let output = quote! {
+        impl #impl_generics iredismodule::io::Digestable for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn digest(&self, digest: &mut iredismodule::io::Digest) {
+                #body
+            }
+        }
+    };
+    TokenStream::from(output)
+}
+
+/// Return the statements digesting `self`
+fn digest_body(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, digests) = digest_fields(&data.fields)?;
+            quote! {
+                let #name #pattern = self;
+                #(#digests)*
+            }
+        }
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .enumerate()
+                .map(|(i, variant)| {
+                    let ident = &variant.ident;
+                    let index = i as i64;
+                    let (pattern, digests) = digest_fields(&variant.fields)?;
+                    Ok(quote! {
+                        #name::#ident #pattern => {
+                            digest.add_integer(#index);
+                            #(#digests)*
+                        }
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "Digestable can't be derived for union",
+            ))
+        }
+    };
+    Ok(body)
+}
+
+/// Return the pattern binding the fields, and the statements digesting them
+fn digest_fields(
+    fields: &Fields,
+) -> syn::Result<(proc_macro2::TokenStream, Vec<proc_macro2::TokenStream>)> {
+    let bindings: Vec<(proc_macro2::TokenStream, Ident, bool)> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let binding = Ident::new(&format!("field{}", i), Span::call_site());
+            let member = match &field.ident {
+                Some(ident) => quote! { #ident },
+                None => {
+                    let index = Index::from(i);
+                    quote! { #index }
+                }
+            };
+            Ok((member, binding, is_skipped(field)?))
+        })
+        .collect::<syn::Result<_>>()?;
+    let members = bindings.iter().map(|(member, binding, _)| {
+        quote! { #member: #binding }
+    });
+    let pattern = match fields {
+        Fields::Unit => quote! {},
+        _ => quote! { { #(#members),* } },
+    };
+    let digests = bindings
+        .iter()
+        .filter(|(_, _, skipped)| !skipped)
+        .map(|(_, binding, _)| {
+            quote! { iredismodule::io::Digestable::digest(#binding, digest); }
+        })
+        .collect();
+    Ok((pattern, digests))
+}
+
+fn is_skipped(field: &syn::Field) -> syn::Result<bool> {
+    let mut skipped = false;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("digest"))
+    {
+        let skip = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list.nested.iter().any(|v| match v {
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) => path.is_ident("skip"),
+                _ => false,
+            }),
+            _ => false,
+        };
+        if !skip {
+            return Err(syn::Error::new_spanned(attr, "expected #[digest(skip)]"));
+        }
+        skipped = true;
+    }
+    Ok(skipped)
+}
@@ -2,6 +2,7 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 
+mod digestable;
 mod rcmd;
 mod rtypedef;
 mod rwrap;
@@ -61,6 +62,14 @@ mod rwrap;
 ///
 /// The las three attrs means first_key, last_key and key_step.
 ///
+/// The `replicate_effects` attr makes the command replicate its effects instead of
+/// itself, see `Context::set_replicate_effects`. It is needed by commands with
+/// the "random" flag which write.
+///
+/// ```rust,no_run
+/// #[rcmd("hello.rand.set", "write random", 1, 1, 1, replicate_effects)]
+/// ```
+///
 /// ```rust,no_run
 /// #[rcmd("hello.simple", "readonly", 0, 0, 0)]
 /// fn hello_simple(ctx: &mut Context, _args: Vec<RStr>) -> RResult {
@@ -123,7 +132,9 @@ pub fn rcmd(attr: TokenStream, input: TokenStream) -> TokenStream {
 
 /// This macro will be used to define a module type.
 ///
-/// It must be used in `impl TypeMethod for T`.
+/// It must be used in `impl TypeMethod for T`, the type can be a path like `module::MyType`
+/// or a concrete instance of a generic type like `Wrapper<u64>`, but the impl itself
+/// must not be generic.
 ///
 /// It have two attr value.
 ///
@@ -145,20 +156,23 @@ pub fn rcmd(attr: TokenStream, input: TokenStream) -> TokenStream {
 ///   callback is able to check the encver value and act accordingly.
 ///   The encver must be a positive value between 0 and 1023.
 ///
+/// Both are checked at compile time, an invalid name or encver is a compile error
+/// instead of a failure of `RedisModule_CreateDataType` when the module is loaded.
+///
 /// ```rust,no_run
 /// #[rtypedef("hellotype", 0)]
 /// impl TypeMethod for HelloTypeNode {
-///     fn rdb_load(io: &mut IO, encver: u32) -> Option<Box<Self>> {
+///     fn rdb_load(io: &mut IO, encver: u32) -> Result<Box<Self>, Error> {
 ///         if encver != 0 {
-///             return None;
+///             return Err(Error::new(format!("unknown encver {}", encver)));
 ///         }
-///         let elements = io.load_unsigned();
+///         let elements = io.try_load_unsigned()?;
 ///         let mut hto = Self::new();
 ///         for _ in 0..elements {
-///             let ele = io.load_signed();
+///             let ele = io.try_load_signed()?;
 ///             hto.push(ele);
 ///         }
-///         Some(Box::new(hto))
+///         Ok(Box::new(hto))
 ///     }
 ///     fn rdb_save(&self, io: &mut IO) {
 ///         let eles: Vec<&i64> = self.iter().collect();
@@ -168,27 +182,29 @@ pub fn rcmd(attr: TokenStream, input: TokenStream) -> TokenStream {
 ///     fn free(_: Box<Self>) {}
 ///     fn aof_rewrite<T: AsRef<str>>(&self, io: &mut IO, key: T) {
 ///         let eles: Vec<&i64> = self.iter().collect();
-///         let keyname = key.as_ref();
-///         eles.iter().for_each(|v| {
-///             io.emit_aof(
-///                 "HELLOTYPE.INSERT",
-///                 &[keyname, &v.to_string()],
-///             )
-///         })
+///         let mut batcher = AofBatcher::new(io, "HELLOTYPE.INSERT", &[key.as_ref()]);
+///         eles.iter().rev().for_each(|v| batcher.push(*v));
 ///     }
 ///     fn mem_usage(&self) -> usize {
 ///         std::mem::size_of::<Self>() * self.len()
 ///     }
 ///     fn digest(&self, digest: &mut Digest) {
 ///         let eles: Vec<&i64> = self.iter().collect();
-///         eles.iter().for_each(|v| digest.add_long_long(**v));
-///         digest.end_sequeue();
+///         eles.iter().for_each(|v| digest.add_integer(**v));
+///         digest.end_sequence();
 ///     }
 /// }
 /// ```
 ///
 /// The macro will generate static variable which repersent the data type. The variabe name
-/// is generated by switching to uppercase and replace "-" with "_".
+/// is generated by switching to uppercase and replace "-" with "_", the `static_name` attr
+/// sets another one.
+/// ```rust,no_run
+/// #[rtypedef("wrapper-1", 0, static_name = "U64_WRAPPER")]
+/// impl TypeMethod for Wrapper<u64> {
+///     // ...
+/// }
+/// ```
 ///
 /// The methods of trait will be expand to extern "C" fn and will be used to set the
 /// value of RedisModuleTypeMethods fields.
@@ -205,14 +221,65 @@ pub fn rcmd(attr: TokenStream, input: TokenStream) -> TokenStream {
 ///     hto.rdb_save(&mut io)
 /// }
 /// ```
+/// The values saved by a newer version of the module, whose encver is greater than the
+/// declared one, are refused.
+///
+/// With the `migrate` attr, the values saved with an older encver are loaded by the listed
+/// `Legacy` encodings, oldest first, and upgraded with `From` until the type.
+/// ```rust,no_run
+/// #[rtypedef("mytype123", 2, migrate(MyTypeV0, MyTypeV1))]
+/// impl TypeMethod for MyType {
+///     // ...
+/// }
+/// ```
+///
+/// With the `serde` attr, `rdb_save` and `rdb_load` are generated from the `Serialize`
+/// and `Deserialize` impls of the type when they are not implemented. It requires the
+/// `serde` feature of `iredismodule`, see the `rdb` module for the encoding.
+/// Values saved with a different encver are refused.
+/// ```rust,no_run
+/// #[rtypedef("mytype123", 0, serde)]
+/// impl TypeMethod for MyType {
+///     fn free(_: Box<Self>) {}
+/// }
+/// ```
+///
+/// With the `aux` attr, `aux_save` and `aux_load` save and load an `AuxState` static when
+/// they are not implemented, at the trigger points of `TypeMethod::AUX_SAVE_TRIGGERS`.
+/// ```rust,no_run
+/// #[rtypedef("mytype123", 0, aux(STATS))]
+/// impl TypeMethod for MyType {
+///     const AUX_SAVE_TRIGGERS: AuxSaveTriggerFlag = AuxSaveTriggerFlag::AuxAfterRdb;
+///     // ...
+/// }
+/// ```
+///
+/// With the `digest` attr, `digest` is generated from the `Digestable` impl of the type
+/// when it is not implemented.
+/// ```rust,no_run
+/// #[derive(Digestable)]
+/// pub struct MyType {
+///     pub data: i64,
+/// }
+///
+/// #[rtypedef("mytype123", 0, digest)]
+/// impl TypeMethod for MyType {
+///     // ...
+/// }
+/// ```
+///
+/// The `Err` returned by `rdb_load` is logged with `IO::log_io_error`, and NULL is
+/// returned to Redis so the load fails cleanly.
+///
 /// If the method is ommited, the value will be set none in construct `RedisModuleTypeMethods`.
+/// The `version` is the oldest one having all the implemented methods.
 ///
 /// ```rust,no_run
 /// pub static HELLOTYPE: iredismodule::rtype::RType<HelloTypeNode> = iredismodule::rtype::RType::new(
 ///     "hellotype",
 ///     0i32,
 ///     iredismodule::raw::RedisModuleTypeMethods {
-///         version: iredismodule::raw::REDISMODULE_TYPE_METHOD_VERSION as u64,
+///         version: 2u64,
 ///         rdb_load: Some(hellotype_rdb_load),
 ///         rdb_save: Some(hellotype_rdb_save),
 ///         aof_rewrite: Some(hellotype_aof_rewrite),
@@ -222,6 +289,14 @@ pub fn rcmd(attr: TokenStream, input: TokenStream) -> TokenStream {
 ///         aux_load: None,
 ///         aux_save: None,
 ///         aux_save_triggers: HelloTypeNode::AUX_SAVE_TRIGGERS as i32,
+///         free_effort: None,
+///         unlink: None,
+///         copy: None,
+///         defrag: None,
+///         mem_usage2: None,
+///         free_effort2: None,
+///         unlink2: None,
+///         copy2: None,
 ///     },
 /// );
 ///
@@ -297,3 +372,20 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
 pub fn rwrap(attr: TokenStream, input: TokenStream) -> TokenStream {
     rwrap::rwrap(attr, input)
 }
+
+/// Derive `iredismodule::io::Digestable`, so the type can be used with `DEBUG DIGEST`.
+///
+/// The fields are added to the digest in order, the fields marked with `#[digest(skip)]`
+/// are ignored. The variant index of an enum is added before its fields.
+/// ```rust,no_run
+/// #[derive(Digestable)]
+/// pub struct MyType {
+///     pub items: Vec<String>,
+///     #[digest(skip)]
+///     pub cache: Option<usize>,
+/// }
+/// ```
+#[proc_macro_derive(Digestable, attributes(digest))]
+pub fn derive_digestable(input: TokenStream) -> TokenStream {
+    digestable::derive_digestable(input)
+}
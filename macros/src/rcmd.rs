@@ -10,6 +10,7 @@ struct CmdAttributeOpts {
     first_key: usize,
     last_key: usize,
     key_step: usize,
+    replicate_effects: bool,
 }
 
 pub fn rcmd(attr: TokenStream, input: TokenStream) -> TokenStream {
@@ -26,6 +27,16 @@ pub fn rcmd(attr: TokenStream, input: TokenStream) -> TokenStream {
     let first_key = opts.first_key;
     let last_key = opts.last_key;
     let key_step = opts.key_step;
+    let set_replicate_effects = if opts.replicate_effects {
+        quote! { context.set_replicate_effects(true); }
+    } else {
+        quote! {}
+    };
+    let set_random_command = if flags.split_whitespace().any(|v| v == "random") {
+        quote! { context.set_random_command(); }
+    } else {
+        quote! {}
+    };
     let raw_fn = quote! {
         #vis extern "C" fn #c_fn_name(
             ctx: *mut iredismodule::raw::RedisModuleCtx,
@@ -34,7 +45,14 @@ pub fn rcmd(attr: TokenStream, input: TokenStream) -> TokenStream {
         ) -> std::os::raw::c_int {
             use iredismodule::FromPtr;
             let mut context = iredismodule::context::Context::from_ptr(ctx);
-            let response = #fn_name(&mut context, iredismodule::parse_args(argv, argc));
+            #set_replicate_effects
+            #set_random_command
+            let mut response = #fn_name(&mut context, iredismodule::parse_args(argv, argc));
+            if let Err(err) = context.flush_replicated() {
+                if response.is_ok() {
+                    response = Err(err);
+                }
+            }
             context.reply(response);
             iredismodule::raw::REDISMODULE_OK as std::os::raw::c_int
         }
@@ -53,6 +71,10 @@ pub fn rcmd(attr: TokenStream, input: TokenStream) -> TokenStream {
 }
 
 fn rcmd_parse_attrs(args: syn::AttributeArgs) -> Option<CmdAttributeOpts> {
+    let replicate_effects = args.iter().any(|v| match v {
+        syn::NestedMeta::Meta(syn::Meta::Path(path)) => path.is_ident("replicate_effects"),
+        _ => false,
+    });
     let lits: Vec<&Lit> = args
         .iter()
         .filter(|v| {
@@ -75,6 +97,7 @@ fn rcmd_parse_attrs(args: syn::AttributeArgs) -> Option<CmdAttributeOpts> {
             first_key: 0,
             last_key: 0,
             key_step: 0,
+            replicate_effects,
         }),
         [Lit::Str(name), Lit::Str(flags)] => Some(CmdAttributeOpts {
             name: name.value(),
@@ -82,6 +105,7 @@ fn rcmd_parse_attrs(args: syn::AttributeArgs) -> Option<CmdAttributeOpts> {
             first_key: 0,
             last_key: 0,
             key_step: 0,
+            replicate_effects,
         }),
         [Lit::Str(name), Lit::Str(flags), Lit::Int(first_key), Lit::Int(last_key), Lit::Int(key_step)] => {
             Some(CmdAttributeOpts {
@@ -90,6 +114,7 @@ fn rcmd_parse_attrs(args: syn::AttributeArgs) -> Option<CmdAttributeOpts> {
                 first_key: first_key.base10_parse().unwrap(),
                 last_key: last_key.base10_parse().unwrap(),
                 key_step: key_step.base10_parse().unwrap(),
+                replicate_effects,
             })
         }
         _ => None,
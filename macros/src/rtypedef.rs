@@ -4,29 +4,54 @@ use quote::quote;
 use std::collections::HashSet;
 use syn::{parse_macro_input, Ident, Lit};
 
-#[derive(Debug)]
+/// The charset of data type names, see `RedisModule_CreateDataType`
+const TYPE_NAME_CHARSET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
 struct TypeDefAttributeOpts {
     name: String,
     version: i32,
+    static_name: Option<Ident>,
+    serde: bool,
+    digest: bool,
+    migrate: Vec<syn::Path>,
+    aux: Option<syn::Path>,
 }
 
 pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
     let item_impl = parse_macro_input!(input as syn::ItemImpl);
     let attr_args = parse_macro_input!(attr as syn::AttributeArgs);
-    let opts = rtypedef_parse_attrs(attr_args).expect("fail to parse attr");
+    let opts = match rtypedef_parse_attrs(attr_args) {
+        Ok(opts) => opts,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    if let Some(param) = item_impl.generics.params.first() {
+        return syn::Error::new_spanned(
+            param,
+            "rtypedef can't be used on a generic impl, implement the trait for a concrete type",
+        )
+        .to_compile_error()
+        .into();
+    }
+    if item_impl.trait_.is_none() {
+        return syn::Error::new_spanned(
+            &item_impl.self_ty,
+            "rtypedef expects an impl of TypeMethod",
+        )
+        .to_compile_error()
+        .into();
+    }
     let type_name_raw = opts.name.as_str();
     let type_version = opts.version;
-    let type_name = type_name_raw.replace("-", "_");
-    let type_static_ident = Ident::new(&type_name.to_ascii_uppercase(), Span::call_site());
-    let data_name_ident = if let syn::Type::Path(syn::TypePath {
-        path: syn::Path { segments, .. },
-        qself: _,
-    }) = item_impl.self_ty.as_ref()
-    {
-        segments.clone().first().unwrap().ident.clone()
-    } else {
-        panic!("expected impl single type");
-    };
+    let type_static_ident = opts.static_name.clone().unwrap_or_else(|| {
+        Ident::new(
+            &type_name_raw.replace("-", "_").to_ascii_uppercase(),
+            Span::call_site(),
+        )
+    });
+    // The wrappers are prefixed with the static name, which is a valid ident
+    let type_name = type_static_ident.to_string().to_ascii_lowercase();
+    let self_ty = item_impl.self_ty.as_ref();
+    let data_type = quote! { #self_ty };
     let method_names: HashSet<String> = item_impl
         .items
         .iter()
@@ -44,37 +69,110 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
         .filter_map(|v| v)
         .collect();
     let have_method = |name: &str| method_names.contains(name);
+    let use_serde = |name: &str| opts.serde && !have_method(name);
 
     let type_name_rdb_load = Ident::new(&format!("{}_rdb_load", &type_name), Span::call_site());
-    let (rdb_load_fn, rdb_load_field) = if have_method("rdb_load") {
+    let load_current = if use_serde("rdb_load") {
+        Some(quote! {
+            if encver == #type_version as u32 {
+                iredismodule::rdb::from_io::<#data_type>(&mut io).map(Box::new)
+            } else {
+                Err(iredismodule::error::Error::new(format!("unknown encver {}", encver)))
+            }
+        })
+    } else if have_method("rdb_load") {
+        Some(quote! {
+            <#data_type>::rdb_load(&mut io, encver)
+        })
+    } else {
+        None
+    };
+    let (rdb_load_fn, rdb_load_field) = if let Some(load_current) = load_current {
+        // Load a legacy encoding, then upgrade it through the newer ones
+        let load_legacies = opts.migrate.iter().enumerate().map(|(i, legacy)| {
+            let upgrades = opts.migrate[i + 1..].iter().map(|next| {
+                quote! { let value: #next = value.into(); }
+            });
+            quote! {
+                else if encver == <#legacy as iredismodule::rtype::Legacy>::ENCVER {
+                    <#legacy as iredismodule::rtype::Legacy>::rdb_load(&mut io).map(|value| {
+                        #(#upgrades)*
+                        let value: #data_type = value.into();
+                        Box::new(value)
+                    })
+                }
+            }
+        });
         (
             quote! {
                 extern "C" fn #type_name_rdb_load(rdb: *mut iredismodule::raw::RedisModuleIO, encver: std::os::raw::c_int) -> *mut std::os::raw::c_void {
                     use iredismodule::FromPtr;
                     let mut io = iredismodule::io::IO::from_ptr(rdb);
-                    let ret = #data_name_ident::rdb_load(&mut io, encver as u32);
-                    if ret.is_none() {
-                        return  0 as *mut std::os::raw::c_void;
+                    let encver = encver as u32;
+                    let result: Result<Box<#data_type>, iredismodule::error::Error> = if encver > #type_version as u32 {
+                        Err(iredismodule::error::Error::new(format!(
+                            "encver {} is newer than {}, the data was saved by a newer version of the module",
+                            encver, #type_version
+                        )))
+                    }
+                    #(#load_legacies)*
+                    else {
+                        #load_current
+                    };
+                    match result {
+                        Ok(value) => Box::into_raw(value) as *mut std::os::raw::c_void,
+                        Err(err) => {
+                            io.log_io_error(
+                                iredismodule::LogLevel::Warning,
+                                &format!("{}: {}", #type_name_raw, err),
+                            );
+                            std::ptr::null_mut()
+                        }
                     }
-                    Box::into_raw(ret.unwrap()) as *mut std::os::raw::c_void
                 }
             },
             quote! {
                 Some(#type_name_rdb_load)
             },
         )
+    } else if !opts.migrate.is_empty() {
+        return syn::Error::new_spanned(
+            &opts.migrate[0],
+            "migrate attr requires rdb_load method or serde attr",
+        )
+        .to_compile_error()
+        .into();
     } else {
         (proc_macro2::TokenStream::new(), quote! { None })
     };
 
     let type_name_rdb_save = Ident::new(&format!("{}_rdb_save", &type_name), Span::call_site());
-    let (rdb_save_fn, rdb_save_field) = if have_method("rdb_save") {
+    let (rdb_save_fn, rdb_save_field) = if use_serde("rdb_save") {
         (
             quote! {
                 unsafe extern "C" fn #type_name_rdb_save(rdb: *mut iredismodule::raw::RedisModuleIO, value: *mut std::os::raw::c_void) {
                     use iredismodule::FromPtr;
                     let mut io = iredismodule::io::IO::from_ptr(rdb);
-                    let hto = &*(value as *mut #data_name_ident);
+                    let hto = &*(value as *mut #data_type);
+                    if let Err(err) = iredismodule::rdb::to_io(&mut io, hto) {
+                        io.log_io_error(
+                            iredismodule::LogLevel::Warning,
+                            &format!("{}: {}", #type_name_raw, err),
+                        );
+                    }
+                }
+            },
+            quote! {
+                Some(#type_name_rdb_save)
+            },
+        )
+    } else if have_method("rdb_save") {
+        (
+            quote! {
+                unsafe extern "C" fn #type_name_rdb_save(rdb: *mut iredismodule::raw::RedisModuleIO, value: *mut std::os::raw::c_void) {
+                    use iredismodule::FromPtr;
+                    let mut io = iredismodule::io::IO::from_ptr(rdb);
+                    let hto = &*(value as *mut #data_type);
                     hto.rdb_save(&mut io)
                 }
             },
@@ -94,7 +192,7 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
                 unsafe extern "C" fn #type_name_aof_rewrite(aof: *mut iredismodule::raw::RedisModuleIO, key: *mut iredismodule::raw::RedisModuleString, value: *mut std::os::raw::c_void) {
                     use iredismodule::FromPtr;
                     let mut io = iredismodule::io::IO::from_ptr(aof);
-                    let hto = &*(value as *mut #data_name_ident);
+                    let hto = &*(value as *mut #data_type);
                     let key = iredismodule::string::RStr::from_ptr(key);
                     hto.aof_rewrite(&mut io, &key)
                 }
@@ -112,7 +210,7 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
         (
             quote! {
                 unsafe extern "C" fn #type_name_mem_usage(value: *const std::os::raw::c_void) -> usize {
-                    let hto = &*(value as *const #data_name_ident);
+                    let hto = &*(value as *const #data_type);
                     hto.mem_usage()
                 }
             },
@@ -125,13 +223,28 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
     };
 
     let type_name_digest = Ident::new(&format!("{}_digest", &type_name), Span::call_site());
-    let (digest_fn, digest_field) = if have_method("digest") {
+    let (digest_fn, digest_field) = if opts.digest && !have_method("digest") {
         (
             quote! {
                 unsafe extern "C" fn #type_name_digest(md: *mut iredismodule::raw::RedisModuleDigest, value: *mut std::os::raw::c_void) {
                     use iredismodule::FromPtr;
                     let mut digest = iredismodule::io::Digest::from_ptr(md);
-                    let hto = &*(value as *const #data_name_ident);
+                    let hto = &*(value as *const #data_type);
+                    iredismodule::io::Digestable::digest(hto, &mut digest);
+                    digest.end_sequence()
+                }
+            },
+            quote! {
+                Some(#type_name_digest)
+            },
+        )
+    } else if have_method("digest") {
+        (
+            quote! {
+                unsafe extern "C" fn #type_name_digest(md: *mut iredismodule::raw::RedisModuleDigest, value: *mut std::os::raw::c_void) {
+                    use iredismodule::FromPtr;
+                    let mut digest = iredismodule::io::Digest::from_ptr(md);
+                    let hto = &*(value as *const #data_type);
                     hto.digest(&mut digest)
                 }
             },
@@ -148,7 +261,7 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
         (
             quote! {
                 unsafe extern "C" fn #type_name_free(value: *mut std::os::raw::c_void) {
-                    #data_name_ident::free(Box::from_raw(value as *mut #data_name_ident))
+                    <#data_type>::free(Box::from_raw(value as *mut #data_type))
                 }
             },
             quote! {
@@ -160,13 +273,30 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
     };
 
     let type_name_aux_load = Ident::new(&format!("{}_aux_load", &type_name), Span::call_site());
-    let (aux_load_fn, aux_load_field) = if have_method("aux_load") {
+    let aux_load_call = if have_method("aux_load") {
+        Some(quote! { <#data_type>::aux_load(&mut io, encver as u32, when) })
+    } else {
+        opts.aux
+            .as_ref()
+            .map(|state| quote! { #state.aux_load(&mut io, encver as u32, when) })
+    };
+    let (aux_load_fn, aux_load_field) = if let Some(aux_load_call) = aux_load_call {
         (
             quote! {
-                unsafe extern "C" fn #type_name_aux_load(rdb: *mut iredismodule::raw::RedisModuleIO, encver: std::os::raw::c_int, when: std::os::raw::c_int) {
+                unsafe extern "C" fn #type_name_aux_load(rdb: *mut iredismodule::raw::RedisModuleIO, encver: std::os::raw::c_int, when: std::os::raw::c_int) -> std::os::raw::c_int {
                     use iredismodule::FromPtr;
                     let mut io = iredismodule::io::IO::from_ptr(rdb);
-                    #data_name_ident::aux_load(&mut io, encver as u32, when as u32)
+                    let when = iredismodule::rtype::AuxWhen::from(when);
+                    match #aux_load_call {
+                        Ok(()) => iredismodule::raw::REDISMODULE_OK as std::os::raw::c_int,
+                        Err(err) => {
+                            io.log_io_error(
+                                iredismodule::LogLevel::Warning,
+                                &format!("{}: {}", #type_name_raw, err),
+                            );
+                            iredismodule::raw::REDISMODULE_ERR as std::os::raw::c_int
+                        }
+                    }
                 }
             },
             quote! {
@@ -178,13 +308,28 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
     };
 
     let type_name_aux_save = Ident::new(&format!("{}_aux_save", &type_name), Span::call_site());
-    let (aux_save_fn, aux_save_field) = if have_method("aux_save") {
+    let aux_save_call = if have_method("aux_save") {
+        Some(quote! { <#data_type>::aux_save(&mut io, when) })
+    } else {
+        opts.aux.as_ref().map(|state| {
+            quote! {
+                if let Err(err) = #state.aux_save(&mut io, when) {
+                    io.log_io_error(
+                        iredismodule::LogLevel::Warning,
+                        &format!("{}: {}", #type_name_raw, err),
+                    );
+                }
+            }
+        })
+    };
+    let (aux_save_fn, aux_save_field) = if let Some(aux_save_call) = aux_save_call {
         (
             quote! {
                 unsafe extern "C" fn #type_name_aux_save(rdb: *mut iredismodule::raw::RedisModuleIO, when: std::os::raw::c_int) {
                     use iredismodule::FromPtr;
                     let mut io = iredismodule::io::IO::from_ptr(rdb);
-                    #data_name_ident::aux_save(&mut io, when as u32)
+                    let when = iredismodule::rtype::AuxWhen::from(when);
+                    #aux_save_call
                 }
             },
             quote! {
@@ -195,16 +340,199 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
         (proc_macro2::TokenStream::new(), quote! { None })
     };
 
+    let type_name_free_effort =
+        Ident::new(&format!("{}_free_effort", &type_name), Span::call_site());
+    let (free_effort_fn, free_effort_field) = if have_method("free_effort") {
+        (
+            quote! {
+                unsafe extern "C" fn #type_name_free_effort(key: *mut iredismodule::raw::RedisModuleString, value: *const std::os::raw::c_void) -> usize {
+                    use iredismodule::FromPtr;
+                    let hto = &*(value as *const #data_type);
+                    let key = iredismodule::string::RStr::from_ptr(key);
+                    hto.free_effort(&key)
+                }
+            },
+            quote! {
+                Some(#type_name_free_effort)
+            },
+        )
+    } else {
+        (proc_macro2::TokenStream::new(), quote! { None })
+    };
+
+    let type_name_unlink = Ident::new(&format!("{}_unlink", &type_name), Span::call_site());
+    let (unlink_fn, unlink_field) = if have_method("unlink") {
+        (
+            quote! {
+                unsafe extern "C" fn #type_name_unlink(key: *mut iredismodule::raw::RedisModuleString, value: *const std::os::raw::c_void) {
+                    use iredismodule::FromPtr;
+                    let hto = &*(value as *const #data_type);
+                    let key = iredismodule::string::RStr::from_ptr(key);
+                    hto.unlink(&key)
+                }
+            },
+            quote! {
+                Some(#type_name_unlink)
+            },
+        )
+    } else {
+        (proc_macro2::TokenStream::new(), quote! { None })
+    };
+
+    let type_name_copy = Ident::new(&format!("{}_copy", &type_name), Span::call_site());
+    let (copy_fn, copy_field) = if have_method("copy") {
+        (
+            quote! {
+                unsafe extern "C" fn #type_name_copy(fromkey: *mut iredismodule::raw::RedisModuleString, tokey: *mut iredismodule::raw::RedisModuleString, value: *const std::os::raw::c_void) -> *mut std::os::raw::c_void {
+                    use iredismodule::FromPtr;
+                    let hto = &*(value as *const #data_type);
+                    let fromkey = iredismodule::string::RStr::from_ptr(fromkey);
+                    let tokey = iredismodule::string::RStr::from_ptr(tokey);
+                    match hto.copy(&fromkey, &tokey) {
+                        Some(value) => Box::into_raw(value) as *mut std::os::raw::c_void,
+                        None => std::ptr::null_mut(),
+                    }
+                }
+            },
+            quote! {
+                Some(#type_name_copy)
+            },
+        )
+    } else {
+        (proc_macro2::TokenStream::new(), quote! { None })
+    };
+
+    let type_name_defrag = Ident::new(&format!("{}_defrag", &type_name), Span::call_site());
+    let (defrag_fn, defrag_field) = if have_method("defrag") {
+        (
+            quote! {
+                unsafe extern "C" fn #type_name_defrag(ctx: *mut iredismodule::raw::RedisModuleDefragCtx, key: *mut iredismodule::raw::RedisModuleString, value: *mut *mut std::os::raw::c_void) -> std::os::raw::c_int {
+                    use iredismodule::FromPtr;
+                    let mut ctx = iredismodule::io::DefragCtx::from_ptr(ctx);
+                    let key = iredismodule::string::RStr::from_ptr(key);
+                    let mut hto = Box::from_raw(*value as *mut #data_type);
+                    let done = <#data_type>::defrag(&mut hto, &mut ctx, &key);
+                    *value = Box::into_raw(hto) as *mut std::os::raw::c_void;
+                    if done {
+                        0
+                    } else {
+                        1
+                    }
+                }
+            },
+            quote! {
+                Some(#type_name_defrag)
+            },
+        )
+    } else {
+        (proc_macro2::TokenStream::new(), quote! { None })
+    };
+
+    let type_name_mem_usage2 = Ident::new(&format!("{}_mem_usage2", &type_name), Span::call_site());
+    let (mem_usage2_fn, mem_usage2_field) = if have_method("mem_usage2") {
+        (
+            quote! {
+                unsafe extern "C" fn #type_name_mem_usage2(ctx: *mut iredismodule::raw::RedisModuleKeyOptCtx, value: *const std::os::raw::c_void, sample_size: usize) -> usize {
+                    use iredismodule::FromPtr;
+                    let ctx = iredismodule::io::KeyOptCtx::from_ptr(ctx);
+                    let hto = &*(value as *const #data_type);
+                    hto.mem_usage2(&ctx, sample_size)
+                }
+            },
+            quote! {
+                Some(#type_name_mem_usage2)
+            },
+        )
+    } else {
+        (proc_macro2::TokenStream::new(), quote! { None })
+    };
+
+    let type_name_free_effort2 =
+        Ident::new(&format!("{}_free_effort2", &type_name), Span::call_site());
+    let (free_effort2_fn, free_effort2_field) = if have_method("free_effort2") {
+        (
+            quote! {
+                unsafe extern "C" fn #type_name_free_effort2(ctx: *mut iredismodule::raw::RedisModuleKeyOptCtx, value: *const std::os::raw::c_void) -> usize {
+                    use iredismodule::FromPtr;
+                    let ctx = iredismodule::io::KeyOptCtx::from_ptr(ctx);
+                    let hto = &*(value as *const #data_type);
+                    hto.free_effort2(&ctx)
+                }
+            },
+            quote! {
+                Some(#type_name_free_effort2)
+            },
+        )
+    } else {
+        (proc_macro2::TokenStream::new(), quote! { None })
+    };
+
+    let type_name_unlink2 = Ident::new(&format!("{}_unlink2", &type_name), Span::call_site());
+    let (unlink2_fn, unlink2_field) = if have_method("unlink2") {
+        (
+            quote! {
+                unsafe extern "C" fn #type_name_unlink2(ctx: *mut iredismodule::raw::RedisModuleKeyOptCtx, value: *const std::os::raw::c_void) {
+                    use iredismodule::FromPtr;
+                    let ctx = iredismodule::io::KeyOptCtx::from_ptr(ctx);
+                    let hto = &*(value as *const #data_type);
+                    hto.unlink2(&ctx)
+                }
+            },
+            quote! {
+                Some(#type_name_unlink2)
+            },
+        )
+    } else {
+        (proc_macro2::TokenStream::new(), quote! { None })
+    };
+
+    let type_name_copy2 = Ident::new(&format!("{}_copy2", &type_name), Span::call_site());
+    let (copy2_fn, copy2_field) = if have_method("copy2") {
+        (
+            quote! {
+                unsafe extern "C" fn #type_name_copy2(ctx: *mut iredismodule::raw::RedisModuleKeyOptCtx, value: *const std::os::raw::c_void) -> *mut std::os::raw::c_void {
+                    use iredismodule::FromPtr;
+                    let ctx = iredismodule::io::KeyOptCtx::from_ptr(ctx);
+                    let hto = &*(value as *const #data_type);
+                    match hto.copy2(&ctx) {
+                        Some(value) => Box::into_raw(value) as *mut std::os::raw::c_void,
+                        None => std::ptr::null_mut(),
+                    }
+                }
+            },
+            quote! {
+                Some(#type_name_copy2)
+            },
+        )
+    } else {
+        (proc_macro2::TokenStream::new(), quote! { None })
+    };
+
+    // The oldest version of RedisModuleTypeMethods having all the methods
+    let methods_version: u64 = if ["mem_usage2", "free_effort2", "unlink2", "copy2"]
+        .iter()
+        .any(|name| have_method(name))
+    {
+        4
+    } else if ["free_effort", "unlink", "copy", "defrag"]
+        .iter()
+        .any(|name| have_method(name))
+    {
+        3
+    } else {
+        2
+    };
+
     let aux_save_triggers = {
-        quote! { #data_name_ident::AUX_SAVE_TRIGGERS as i32 }
+        quote! { <#data_type>::AUX_SAVE_TRIGGERS as i32 }
     };
 
     let type_static = quote! {
-        pub static #type_static_ident: iredismodule::rtype::RType<#data_name_ident> = iredismodule::rtype::RType::new(
+        pub static #type_static_ident: iredismodule::rtype::RType<#data_type> = iredismodule::rtype::RType::new(
             #type_name_raw,
             #type_version,
             iredismodule::raw::RedisModuleTypeMethods {
-                version: iredismodule::raw::REDISMODULE_TYPE_METHOD_VERSION as u64,
+                version: #methods_version,
                 rdb_load: #rdb_load_field,
                 rdb_save: #rdb_save_field,
                 aof_rewrite: #aof_rewrite_field,
@@ -214,6 +542,14 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
                 aux_load: #aux_load_field,
                 aux_save: #aux_save_field,
                 aux_save_triggers: #aux_save_triggers,
+                free_effort: #free_effort_field,
+                unlink: #unlink_field,
+                copy: #copy_field,
+                defrag: #defrag_field,
+                mem_usage2: #mem_usage2_field,
+                free_effort2: #free_effort2_field,
+                unlink2: #unlink2_field,
+                copy2: #copy2_field,
             },
         );
     };
@@ -228,35 +564,128 @@ pub fn rtypedef(attr: TokenStream, input: TokenStream) -> TokenStream {
         #free_fn
         #aux_load_fn
         #aux_save_fn
+        #free_effort_fn
+        #unlink_fn
+        #copy_fn
+        #defrag_fn
+        #mem_usage2_fn
+        #free_effort2_fn
+        #unlink2_fn
+        #copy2_fn
         #item_impl
     };
     TokenStream::from(output)
 }
 
-fn rtypedef_parse_attrs(args: syn::AttributeArgs) -> Option<TypeDefAttributeOpts> {
-    let lits: Vec<&Lit> = args
-        .iter()
-        .filter(|v| {
-            if let syn::NestedMeta::Lit(_) = v {
-                true
-            } else {
-                false
+fn rtypedef_parse_attrs(args: syn::AttributeArgs) -> Result<TypeDefAttributeOpts, syn::Error> {
+    let mut lits = vec![];
+    let mut opts = TypeDefAttributeOpts {
+        name: String::new(),
+        version: 0,
+        static_name: None,
+        serde: false,
+        digest: false,
+        migrate: vec![],
+        aux: None,
+    };
+    for arg in args.iter() {
+        match arg {
+            syn::NestedMeta::Lit(lit) => lits.push(lit),
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("serde") => {
+                opts.serde = true
             }
-        })
-        .map(|v| match v {
-            syn::NestedMeta::Lit(i) => i,
-            _ => unreachable!(),
-        })
-        .collect();
-    match lits.as_slice() {
-        [Lit::Str(name)] => Some(TypeDefAttributeOpts {
-            name: name.value(),
-            version: 0,
-        }),
-        [Lit::Str(name), Lit::Int(version)] => Some(TypeDefAttributeOpts {
-            name: name.value(),
-            version: version.base10_parse().unwrap(),
-        }),
-        _ => None,
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("digest") => {
+                opts.digest = true
+            }
+            syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("migrate") => {
+                for v in list.nested.iter() {
+                    match v {
+                        syn::NestedMeta::Meta(syn::Meta::Path(path)) => {
+                            opts.migrate.push(path.clone())
+                        }
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                v,
+                                "migrate attr expects a list of types",
+                            ))
+                        }
+                    }
+                }
+            }
+            syn::NestedMeta::Meta(syn::Meta::List(list)) if list.path.is_ident("aux") => {
+                match list.nested.iter().collect::<Vec<_>>().as_slice() {
+                    [syn::NestedMeta::Meta(syn::Meta::Path(path))] => opts.aux = Some(path.clone()),
+                    _ => return Err(syn::Error::new_spanned(list, "aux attr expects a static")),
+                }
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                lit: Lit::Str(name),
+                ..
+            })) if path.is_ident("static_name") => {
+                let ident = name.parse::<Ident>().map_err(|_| {
+                    syn::Error::new_spanned(name, "static_name expects an identifier")
+                })?;
+                opts.static_name = Some(ident);
+            }
+            _ => return Err(syn::Error::new_spanned(arg, "unknown rtypedef attr")),
+        }
+    }
+    let name = match lits.as_slice() {
+        [Lit::Str(name)] => name,
+        [Lit::Str(name), Lit::Int(version)] => {
+            opts.version = check_type_version(version)?;
+            name
+        }
+        _ => {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "expected #[rtypedef(\"name\", encver)]",
+            ))
+        }
+    };
+    opts.name = check_type_name(name)?;
+    if opts.static_name.is_none() && opts.name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(syn::Error::new_spanned(
+            name,
+            "the name starts with a digit, set the name of the static with `static_name`",
+        ));
+    }
+    Ok(opts)
+}
+
+/// Check the name like `RedisModule_CreateDataType` does
+fn check_type_name(name: &syn::LitStr) -> Result<String, syn::Error> {
+    let value = name.value();
+    if value.len() != 9 {
+        return Err(syn::Error::new_spanned(
+            name,
+            format!(
+                "the name of a data type must be exactly 9 characters, '{}' has {}",
+                value,
+                value.chars().count()
+            ),
+        ));
+    }
+    if let Some(c) = value.chars().find(|c| !TYPE_NAME_CHARSET.contains(*c)) {
+        return Err(syn::Error::new_spanned(
+            name,
+            format!(
+                "invalid character '{}' in the name of a data type, use A-Z a-z 0-9 - _",
+                c
+            ),
+        ));
+    }
+    Ok(value)
+}
+
+/// The encver is stored in the 10 lower bits of the module type id
+fn check_type_version(version: &syn::LitInt) -> Result<i32, syn::Error> {
+    match version.base10_parse::<i32>() {
+        Ok(v) if (0..=1023).contains(&v) => Ok(v),
+        _ => Err(syn::Error::new_spanned(
+            version,
+            "the encver of a data type must be between 0 and 1023",
+        )),
     }
 }
@@ -0,0 +1,120 @@
+//! Arguments of `Context::call` and `Context::replicate`
+
+use crate::string::{RStr, RString};
+use std::ops::Deref;
+
+/// A command argument, either borrowed from a Redis string or created for the call
+pub enum CallArg<'a> {
+    Borrowed(&'a RStr),
+    Owned(RString),
+}
+
+impl<'a> Deref for CallArg<'a> {
+    type Target = RStr;
+    fn deref(&self) -> &Self::Target {
+        match self {
+            CallArg::Borrowed(v) => v,
+            CallArg::Owned(v) => v.get_rstr(),
+        }
+    }
+}
+
+impl<'a> CallArg<'a> {
+    /// Convert to an owned string, the borrowed string is copied
+    pub fn into_owned(self) -> RString {
+        match self {
+            CallArg::Borrowed(v) => RString::from_bytes(v.get_buffer()),
+            CallArg::Owned(v) => v,
+        }
+    }
+}
+
+/// Convert a value to an argument of `Context::call` and `Context::replicate`.
+///
+/// `RStr` and `RString` are passed as they are, without copying them.
+///
+/// Arguments of different types can be mixed in a slice of `&dyn ToCallArg`,
+/// or with the [`call!`](../macro.call.html) macro.
+/// ```rust,no_run
+/// let args: [&dyn ToCallArg; 4] = [&args[1], &b"\x00\x01"[..], &"EX", &10];
+/// ctx.call("SET", None, &args)?;
+/// ```
+pub trait ToCallArg {
+    fn to_call_arg(&self) -> CallArg<'_>;
+}
+
+impl<T: ToCallArg + ?Sized> ToCallArg for &T {
+    fn to_call_arg(&self) -> CallArg<'_> {
+        (**self).to_call_arg()
+    }
+}
+
+impl ToCallArg for CallArg<'_> {
+    fn to_call_arg(&self) -> CallArg<'_> {
+        CallArg::Borrowed(self)
+    }
+}
+
+impl ToCallArg for RStr {
+    fn to_call_arg(&self) -> CallArg<'_> {
+        CallArg::Borrowed(self)
+    }
+}
+
+impl ToCallArg for RString {
+    fn to_call_arg(&self) -> CallArg<'_> {
+        CallArg::Borrowed(self.get_rstr())
+    }
+}
+
+impl ToCallArg for str {
+    fn to_call_arg(&self) -> CallArg<'_> {
+        CallArg::Owned(RString::from_str(self))
+    }
+}
+
+impl ToCallArg for String {
+    fn to_call_arg(&self) -> CallArg<'_> {
+        CallArg::Owned(RString::from_str(self))
+    }
+}
+
+impl ToCallArg for [u8] {
+    fn to_call_arg(&self) -> CallArg<'_> {
+        CallArg::Owned(RString::from_bytes(self))
+    }
+}
+
+impl ToCallArg for Vec<u8> {
+    fn to_call_arg(&self) -> CallArg<'_> {
+        CallArg::Owned(RString::from_bytes(self))
+    }
+}
+
+macro_rules! impl_to_call_arg_integer {
+    ($($t:ty),*) => {
+        $(
+            impl ToCallArg for $t {
+                fn to_call_arg(&self) -> CallArg<'_> {
+                    CallArg::Owned(RString::from_integer(*self as i64))
+                }
+            }
+        )*
+    };
+}
+
+impl_to_call_arg_integer!(i8, i16, i32, i64, isize, u8, u16, u32);
+
+macro_rules! impl_to_call_arg_display {
+    ($($t:ty),*) => {
+        $(
+            impl ToCallArg for $t {
+                fn to_call_arg(&self) -> CallArg<'_> {
+                    CallArg::Owned(RString::from_str(self.to_string()))
+                }
+            }
+        )*
+    };
+}
+
+impl_to_call_arg_display!(u64, usize, f32, f64);
@@ -2,10 +2,20 @@
 
 use crate::error::Error;
 use crate::raw;
+use crate::resp;
 use crate::value::Value;
 use crate::{FromPtr, GetPtr, RResult};
 
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::hash::Hash;
+use std::ops::{Deref, Index};
+use std::os::raw::c_char;
+
 /// Wrap the pointer of a RedisModuleCallReply
+///
+/// The reply is freed on drop, all the accessors are provided by the
+/// borrowed view `CallReplyRef`.
 #[repr(C)]
 pub struct CallReply {
     ptr: *mut raw::RedisModuleCallReply,
@@ -25,142 +35,543 @@ impl FromPtr for CallReply {
     }
 }
 
-impl CallReply {
+impl Deref for CallReply {
+    type Target = CallReplyRef;
+    fn deref(&self) -> &Self::Target {
+        unsafe { CallReplyRef::from_raw(self.ptr) }
+    }
+}
+
+impl Drop for CallReply {
+    fn drop(&mut self) {
+        unsafe { raw::RedisModule_FreeCallReply.unwrap()(self.ptr) }
+    }
+}
+
+impl From<CallReply> for RResult {
+    fn from(reply: CallReply) -> RResult {
+        (&*reply).into()
+    }
+}
+
+/// A reply, or a nested element of a reply, borrowed from the `CallReply`
+/// which owns it, so walking a reply does not allocate.
+#[repr(transparent)]
+pub struct CallReplyRef {
+    inner: raw::RedisModuleCallReply,
+}
+
+impl GetPtr for CallReplyRef {
+    type PtrType = raw::RedisModuleCallReply;
+    fn get_ptr(&self) -> *mut Self::PtrType {
+        &self.inner as *const raw::RedisModuleCallReply as *mut raw::RedisModuleCallReply
+    }
+}
+
+impl CallReplyRef {
+    /// Borrow the reply pointed by `ptr`, which must outlive `'a`
+    unsafe fn from_raw<'a>(ptr: *mut raw::RedisModuleCallReply) -> &'a CallReplyRef {
+        &*(ptr as *const CallReplyRef)
+    }
+    /// Borrow the nested reply pointed by `ptr`, None if it is NULL
+    fn nested(&self, ptr: *mut raw::RedisModuleCallReply) -> Option<&CallReplyRef> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(unsafe { CallReplyRef::from_raw(ptr) })
+        }
+    }
     /// Return the reply type
     pub fn get_type(&self) -> ReplyType {
-        let x = unsafe { raw::RedisModule_CallReplyType.unwrap()(self.ptr) as u32 };
+        let x = unsafe { raw::RedisModule_CallReplyType.unwrap()(self.get_ptr()) as u32 };
         match x {
             raw::REDISMODULE_REPLY_STRING => ReplyType::String,
             raw::REDISMODULE_REPLY_ERROR => ReplyType::Error,
             raw::REDISMODULE_REPLY_INTEGER => ReplyType::Integer,
             raw::REDISMODULE_REPLY_ARRAY => ReplyType::Array,
             raw::REDISMODULE_REPLY_NULL => ReplyType::Null,
+            raw::REDISMODULE_REPLY_MAP => ReplyType::Map,
+            raw::REDISMODULE_REPLY_SET => ReplyType::Set,
+            raw::REDISMODULE_REPLY_BOOL => ReplyType::Bool,
+            raw::REDISMODULE_REPLY_DOUBLE => ReplyType::Double,
+            raw::REDISMODULE_REPLY_BIG_NUMBER => ReplyType::BigNumber,
+            raw::REDISMODULE_REPLY_VERBATIM_STRING => ReplyType::Verbatim,
+            raw::REDISMODULE_REPLY_ATTRIBUTE => ReplyType::Attribute,
             _ => ReplyType::Unknown,
         }
     }
-    /// Get the string value from a string type reply
-    pub fn get_string(&self) -> Result<String, Error> {
-        if self.get_type() != ReplyType::String {
-            return Err(Error::new("Reply type is not string"));
+    /// Return the buffer of a string or error reply, without copying it
+    pub fn get_bytes(&self) -> Result<&[u8], Error> {
+        match self.get_type() {
+            ReplyType::String | ReplyType::Error => {}
+            _ => return Err(Error::new("Reply type is not string")),
         }
-        let buf = self.get_proto();
-        if buf[0] == 36 {
-            return Ok(String::from_utf8(proto_to_buff_string(buf)).unwrap());
-        } else {
-            return Ok(proto_to_string(buf));
+        let mut len = 0;
+        let ptr = unsafe { raw::RedisModule_CallReplyStringPtr.unwrap()(self.get_ptr(), &mut len) };
+        if ptr.is_null() {
+            return Ok(&[]);
         }
+        Ok(unsafe { std::slice::from_raw_parts(ptr as *const u8, len) })
+    }
+    /// Return the utf8 string of a string or error reply, without copying it
+    pub fn get_str(&self) -> Result<&str, Error> {
+        Ok(std::str::from_utf8(self.get_bytes()?)?)
+    }
+    /// Get the string value from a string or error type reply
+    pub fn get_string(&self) -> Result<String, Error> {
+        Ok(self.get_str()?.to_owned())
     }
     /// Return the bulk string buffer
     pub fn get_bulk_string(&self) -> Result<Vec<u8>, Error> {
         if self.get_type() != ReplyType::String {
             return Err(Error::new("Reply type is not bulk string"));
         }
-        Ok(proto_to_buff_string(self.get_proto()))
+        Ok(self.get_bytes()?.to_vec())
     }
-    /// Return the double value
+    /// Return the double value of a double reply, or of a string reply
+    /// holding a double
     pub fn get_double(&self) -> Result<f64, Error> {
-        if self.get_type() != ReplyType::String {
-            return Err(Error::new("Reply type is not double"));
+        match self.get_type() {
+            ReplyType::Double => {
+                Ok(unsafe { raw::RedisModule_CallReplyDouble.unwrap()(self.get_ptr()) })
+            }
+            ReplyType::String => Ok(self.get_str()?.parse::<f64>()?),
+            _ => Err(Error::new("Reply type is not double")),
         }
-        let value = proto_to_buff_string(self.get_proto());
-        let value_str = std::str::from_utf8(&value)?;
-        Ok(value_str.parse::<f64>()?)
     }
     /// Get the integer value from a integer type reply
     pub fn get_integer(&self) -> Result<i64, Error> {
         if self.get_type() != ReplyType::Integer {
             return Err(Error::new("Reply type is not integer"));
         }
-        Ok(unsafe { raw::RedisModule_CallReplyInteger.unwrap()(self.ptr) })
+        Ok(unsafe { raw::RedisModule_CallReplyInteger.unwrap()(self.get_ptr()) })
     }
-    /// Return the 'idx'-th nested call reply element of an array reply, or None
-    /// if the reply type is wrong or the index is out of range
-    pub fn get_array_element(&self, idx: usize) -> Option<CallReply> {
-        let ptr = unsafe { raw::RedisModule_CallReplyArrayElement.unwrap()(self.ptr, idx) };
-        if ptr.is_null() {
-            None
-        } else {
-            Some(CallReply::from_ptr(ptr))
+    /// Get the value of a bool reply
+    pub fn get_bool(&self) -> Result<bool, Error> {
+        if self.get_type() != ReplyType::Bool {
+            return Err(Error::new("Reply type is not bool"));
+        }
+        Ok(unsafe { raw::RedisModule_CallReplyBool.unwrap()(self.get_ptr()) != 0 })
+    }
+    /// Get the decimal digits of a big number reply
+    pub fn get_big_number(&self) -> Result<&str, Error> {
+        if self.get_type() != ReplyType::BigNumber {
+            return Err(Error::new("Reply type is not big number"));
+        }
+        let mut len = 0;
+        let ptr = unsafe { raw::RedisModule_CallReplyBigNumber.unwrap()(self.get_ptr(), &mut len) };
+        let buf = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+        Ok(std::str::from_utf8(buf)?)
+    }
+    /// Get the format (e.g. "txt" or "mkd") and the content of a verbatim string reply
+    pub fn get_verbatim(&self) -> Result<(&str, &[u8]), Error> {
+        if self.get_type() != ReplyType::Verbatim {
+            return Err(Error::new("Reply type is not verbatim string"));
+        }
+        let mut len = 0;
+        let mut format: *const c_char = std::ptr::null();
+        let ptr = unsafe {
+            raw::RedisModule_CallReplyVerbatim.unwrap()(self.get_ptr(), &mut len, &mut format)
+        };
+        let buf = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+        // The format is always 3 chars long
+        let format = unsafe { std::slice::from_raw_parts(format as *const u8, 3) };
+        Ok((std::str::from_utf8(format)?, buf))
+    }
+    /// Return the 'idx'-th nested call reply element of an array or set reply,
+    /// or None if the reply type is wrong or the index is out of range
+    pub fn get_array_element(&self, idx: usize) -> Option<&CallReplyRef> {
+        let ptr = match self.get_type() {
+            ReplyType::Array => unsafe {
+                raw::RedisModule_CallReplyArrayElement.unwrap()(self.get_ptr(), idx)
+            },
+            ReplyType::Set => unsafe {
+                raw::RedisModule_CallReplySetElement.unwrap()(self.get_ptr(), idx)
+            },
+            _ => return None,
+        };
+        self.nested(ptr)
+    }
+    /// Return the 'idx'-th key and value of a map reply, or None if the reply
+    /// type is wrong or the index is out of range
+    pub fn get_map_element(&self, idx: usize) -> Option<(&CallReplyRef, &CallReplyRef)> {
+        if self.get_type() != ReplyType::Map {
+            return None;
+        }
+        let mut key = std::ptr::null_mut();
+        let mut value = std::ptr::null_mut();
+        let status = unsafe {
+            raw::RedisModule_CallReplyMapElement.unwrap()(self.get_ptr(), idx, &mut key, &mut value)
+        };
+        if status != raw::REDISMODULE_OK as i32 {
+            return None;
+        }
+        Some((self.nested(key)?, self.nested(value)?))
+    }
+    /// Return the attribute reply sent along with the reply, if any
+    pub fn get_attribute(&self) -> Option<&CallReplyRef> {
+        let api = unsafe { raw::RedisModule_CallReplyAttribute }?;
+        self.nested(unsafe { api(self.get_ptr()) })
+    }
+    /// Return the 'idx'-th key and value of an attribute reply, or None if the
+    /// reply type is wrong or the index is out of range
+    pub fn get_attribute_element(&self, idx: usize) -> Option<(&CallReplyRef, &CallReplyRef)> {
+        if self.get_type() != ReplyType::Attribute {
+            return None;
+        }
+        let mut key = std::ptr::null_mut();
+        let mut value = std::ptr::null_mut();
+        let status = unsafe {
+            raw::RedisModule_CallReplyAttributeElement.unwrap()(
+                self.get_ptr(),
+                idx,
+                &mut key,
+                &mut value,
+            )
+        };
+        if status != raw::REDISMODULE_OK as i32 {
+            return None;
+        }
+        Some((self.nested(key)?, self.nested(value)?))
+    }
+    /// Iterate the elements of an array or set reply
+    pub fn iter(&self) -> CallReplyIter<'_> {
+        CallReplyIter {
+            reply: self,
+            idx: 0,
+        }
+    }
+    /// Iterate the keys and values of a map or attribute reply
+    pub fn map_iter(&self) -> CallReplyMapIter<'_> {
+        CallReplyMapIter {
+            reply: self,
+            idx: 0,
         }
     }
     // Return raw proto buffer
     pub fn get_proto(&self) -> Vec<u8> {
         unsafe {
             let mut len = 0;
-            let ptr = raw::RedisModule_CallReplyProto.unwrap()(self.ptr, &mut len);
+            let ptr = raw::RedisModule_CallReplyProto.unwrap()(self.get_ptr(), &mut len);
             let data: &[u8] = std::slice::from_raw_parts(ptr as *const u8, len);
             data.to_vec()
         }
     }
-    /// Return the length of array reply type.
+    /// Return the length of array, set, map, attribute, string or error reply.
     ///
-    /// If the reply is not array type, 0 is returned
+    /// For other types of reply, 0 is returned
     pub fn get_length(&self) -> usize {
-        unsafe { raw::RedisModule_CallReplyLength.unwrap()(self.ptr) }
+        unsafe { raw::RedisModule_CallReplyLength.unwrap()(self.get_ptr()) }
     }
 }
 
-impl Drop for CallReply {
-    fn drop(&mut self) {
-        unsafe { raw::RedisModule_FreeCallReply.unwrap()(self.ptr) }
+impl Index<usize> for CallReplyRef {
+    type Output = CallReplyRef;
+    /// Panics if the reply is not an array or set, or the index is out of range
+    fn index(&self, idx: usize) -> &Self::Output {
+        match self.get_array_element(idx) {
+            Some(v) => v,
+            None => panic!(
+                "index {} out of range for {:?} reply of length {}",
+                idx,
+                self.get_type(),
+                self.get_length()
+            ),
+        }
     }
 }
 
-impl Into<RResult> for CallReply {
-    fn into(self) -> RResult {
-        let reply_type = self.get_type();
-        match reply_type {
-            ReplyType::Error => Err(Error::new(&self.get_string().unwrap())),
-            ReplyType::Unknown => Err(Error::new("Unkown reply type")),
-            ReplyType::Array => {
-                let length = self.get_length();
-                let mut vec = Vec::with_capacity(length);
-                for i in 0..length {
-                    let value: RResult = self.get_array_element(i).unwrap().into();
-                    vec.push(value?)
+impl<'a> IntoIterator for &'a CallReplyRef {
+    type Item = &'a CallReplyRef;
+    type IntoIter = CallReplyIter<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a CallReply {
+    type Item = &'a CallReplyRef;
+    type IntoIter = CallReplyIter<'a>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the elements of an array or set reply
+pub struct CallReplyIter<'a> {
+    reply: &'a CallReplyRef,
+    idx: usize,
+}
+
+impl<'a> Iterator for CallReplyIter<'a> {
+    type Item = &'a CallReplyRef;
+    fn next(&mut self) -> Option<Self::Item> {
+        let elem = self.reply.get_array_element(self.idx)?;
+        self.idx += 1;
+        Some(elem)
+    }
+}
+
+/// Iterator over the keys and values of a map or attribute reply
+pub struct CallReplyMapIter<'a> {
+    reply: &'a CallReplyRef,
+    idx: usize,
+}
+
+impl<'a> Iterator for CallReplyMapIter<'a> {
+    type Item = (&'a CallReplyRef, &'a CallReplyRef);
+    fn next(&mut self) -> Option<Self::Item> {
+        let elem = match self.reply.get_type() {
+            ReplyType::Attribute => self.reply.get_attribute_element(self.idx),
+            _ => self.reply.get_map_element(self.idx),
+        }?;
+        self.idx += 1;
+        Some(elem)
+    }
+}
+
+/// An error reply is converted to `Err`, the errors nested in an array are
+/// kept as `Value::Error`
+impl From<&CallReplyRef> for RResult {
+    fn from(reply: &CallReplyRef) -> RResult {
+        match reply.to_value() {
+            Value::Error(msg) => Err(Error::new(msg)),
+            value => Ok(value),
+        }
+    }
+}
+
+impl From<&CallReplyRef> for Value {
+    fn from(reply: &CallReplyRef) -> Value {
+        reply.to_value()
+    }
+}
+
+impl From<CallReply> for Value {
+    fn from(reply: CallReply) -> Value {
+        reply.to_value()
+    }
+}
+
+impl CallReplyRef {
+    /// Convert the reply to a value, replying with the value produces the same
+    /// RESP2 bytes as `get_proto`.
+    ///
+    /// Error replies become `Value::Error`, even nested ones. Of the RESP3 types,
+    /// maps are flattened to arrays of keys and values.
+    fn to_value(&self) -> Value {
+        match self.get_type() {
+            ReplyType::Error => Value::Error(self.get_lossy_string()),
+            ReplyType::Unknown | ReplyType::Attribute => {
+                Value::Error("ERR unknown reply type".to_owned())
+            }
+            ReplyType::Array | ReplyType::Set => {
+                Value::Array(self.iter().map(|elem| elem.to_value()).collect())
+            }
+            ReplyType::Map => {
+                let mut vec = Vec::with_capacity(self.get_length() * 2);
+                for (key, value) in self.map_iter() {
+                    vec.push(key.to_value());
+                    vec.push(value.to_value());
                 }
-                Ok(Value::Array(vec))
+                Value::Array(vec)
+            }
+            ReplyType::Integer => Value::Integer(self.get_integer().unwrap_or_default()),
+            ReplyType::Bool => Value::Integer(self.get_bool().unwrap_or_default() as i64),
+            ReplyType::Double => Value::Double(self.get_double().unwrap_or_default()),
+            ReplyType::BigNumber => {
+                Value::BigNumber(self.get_big_number().unwrap_or_default().to_owned())
             }
-            ReplyType::Integer => Ok(Value::Integer(self.get_integer().unwrap())),
+            ReplyType::Verbatim => Value::BulkString(
+                self.get_verbatim()
+                    .map(|v| v.1.to_vec())
+                    .unwrap_or_default(),
+            ),
             ReplyType::String => {
-                let buf = self.get_proto();
-                if buf[0] == 36 {
-                    // bulk string
-                    Ok(Value::BulkString(proto_to_buff_string(buf)))
-                } else if buf[0] == 43 {
-                    // simple string
-                    Ok(Value::String(proto_to_string(buf)))
+                if self.proto_prefix() == Some(b'+') {
+                    Value::String(self.get_lossy_string())
+                } else {
+                    Value::BulkString(self.get_bytes().unwrap_or_default().to_vec())
+                }
+            }
+            ReplyType::Null => {
+                // A null array is "*-1", a null bulk string is "$-1"
+                if self.proto_prefix() == Some(b'*') {
+                    Value::NullArray
                 } else {
-                    Err(Error::new("Invalid string reply"))
+                    Value::Null
+                }
+            }
+        }
+    }
+    fn get_lossy_string(&self) -> String {
+        String::from_utf8_lossy(self.get_bytes().unwrap_or_default()).into_owned()
+    }
+    fn proto_prefix(&self) -> Option<u8> {
+        let mut len = 0;
+        let ptr = unsafe { raw::RedisModule_CallReplyProto.unwrap()(self.get_ptr(), &mut len) };
+        if ptr.is_null() || len == 0 {
+            return None;
+        }
+        Some(unsafe { *(ptr as *const u8) })
+    }
+}
+
+impl CallReplyRef {
+    /// Convert the reply to a value by decoding the buffer of `get_proto`,
+    /// see `resp::decode`
+    pub fn to_value_via_proto(&self) -> RResult {
+        resp::decode(&self.get_proto())
+    }
+    /// Convert the reply to a rust type, error replies are returned as `Err`
+    ///
+    /// ```rust,no_run
+    /// let reply = call!(ctx, "HGETALL", &args[1])?;
+    /// let fields: HashMap<String, i64> = reply.parse()?;
+    /// ```
+    pub fn parse<T: FromCallReply>(&self) -> Result<T, Error> {
+        if self.get_type() == ReplyType::Error {
+            return Err(Error::new(self.get_str()?));
+        }
+        T::from_call_reply(self)
+    }
+}
+
+/// Convert a call reply to a rust type, see `CallReplyRef::parse`
+///
+/// Conversions are lenient in the same way as the redis clients: a string reply
+/// holding a number can be read as a number, and an integer reply can be read
+/// as a string or a bool.
+pub trait FromCallReply: Sized {
+    fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error>;
+}
+
+impl FromCallReply for Value {
+    fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error> {
+        Ok(reply.to_value())
+    }
+}
+
+impl FromCallReply for () {
+    fn from_call_reply(_reply: &CallReplyRef) -> Result<Self, Error> {
+        Ok(())
+    }
+}
+
+impl FromCallReply for i64 {
+    fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error> {
+        match reply.get_type() {
+            ReplyType::Integer => reply.get_integer(),
+            ReplyType::Bool => Ok(reply.get_bool()? as i64),
+            ReplyType::String => Ok(reply.get_str()?.parse::<i64>()?),
+            _ => Err(Error::new("Reply type is not integer")),
+        }
+    }
+}
+
+macro_rules! impl_from_call_reply_integer {
+    ($($t:ty),*) => {
+        $(
+            impl FromCallReply for $t {
+                fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error> {
+                    let value = i64::from_call_reply(reply)?;
+                    <$t>::try_from(value).map_err(|_| Error::new("Reply integer is out of range"))
                 }
             }
-            ReplyType::Null => Ok(Value::Null),
+        )*
+    };
+}
+
+impl_from_call_reply_integer!(i32, u32, u64, usize);
+
+impl FromCallReply for f64 {
+    fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error> {
+        match reply.get_type() {
+            ReplyType::Integer => Ok(reply.get_integer()? as f64),
+            _ => reply.get_double(),
+        }
+    }
+}
+
+impl FromCallReply for bool {
+    fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error> {
+        match reply.get_type() {
+            ReplyType::Bool => reply.get_bool(),
+            ReplyType::Null => Ok(false),
+            _ => Ok(i64::from_call_reply(reply)? != 0),
+        }
+    }
+}
+
+impl FromCallReply for String {
+    fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error> {
+        match reply.get_type() {
+            ReplyType::String => reply.get_string(),
+            ReplyType::Integer => Ok(reply.get_integer()?.to_string()),
+            ReplyType::Double => Ok(reply.get_double()?.to_string()),
+            ReplyType::BigNumber => Ok(reply.get_big_number()?.to_owned()),
+            ReplyType::Verbatim => Ok(std::str::from_utf8(reply.get_verbatim()?.1)?.to_owned()),
+            _ => Err(Error::new("Reply type is not string")),
+        }
+    }
+}
+
+impl FromCallReply for Vec<u8> {
+    fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error> {
+        match reply.get_type() {
+            ReplyType::Verbatim => Ok(reply.get_verbatim()?.1.to_vec()),
+            ReplyType::Array | ReplyType::Set => Err(Error::new("Reply type is not string")),
+            _ => Ok(String::from_call_reply(reply)?.into_bytes()),
+        }
+    }
+}
+
+impl<T: FromCallReply> FromCallReply for Option<T> {
+    fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error> {
+        if reply.get_type() == ReplyType::Null {
+            return Ok(None);
         }
+        Ok(Some(reply.parse()?))
     }
 }
 
-fn proto_to_buff_string(buf: Vec<u8>) -> Vec<u8> {
-    if buf[1] == 45 {
-        // empty bulk string
-        return vec![];
+impl<T: FromCallReply> FromCallReply for Vec<T> {
+    fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error> {
+        match reply.get_type() {
+            ReplyType::Array | ReplyType::Set => reply.iter().map(|elem| elem.parse()).collect(),
+            ReplyType::Null => Ok(vec![]),
+            _ => Err(Error::new("Reply type is not array")),
+        }
     }
-    let len_buf = buf
-        .iter()
-        .skip(1)
-        .take_while(|v| **v != 13)
-        .cloned()
-        .collect::<Vec<u8>>();
-    let len: usize = String::from_utf8(len_buf).unwrap().parse().unwrap();
-    buf.into_iter()
-        .skip_while(|v| *v != 10)
-        .skip(1)
-        .take(len)
-        .collect()
 }
 
-fn proto_to_string(buf: Vec<u8>) -> String {
-    let value: Vec<u8> = buf.into_iter().skip(1).take_while(|v| *v != 13).collect();
-    std::str::from_utf8(&value).unwrap().to_owned()
+impl<K, V> FromCallReply for HashMap<K, V>
+where
+    K: FromCallReply + Eq + Hash,
+    V: FromCallReply,
+{
+    /// Read a map reply, or a RESP2 array of alternating keys and values
+    fn from_call_reply(reply: &CallReplyRef) -> Result<Self, Error> {
+        match reply.get_type() {
+            ReplyType::Map => reply
+                .map_iter()
+                .map(|(key, value)| Ok((key.parse()?, value.parse()?)))
+                .collect(),
+            ReplyType::Array => {
+                if reply.get_length() % 2 == 1 {
+                    return Err(Error::new("Reply array has an odd number of elements"));
+                }
+                let mut map = HashMap::with_capacity(reply.get_length() / 2);
+                let mut iter = reply.iter();
+                while let (Some(key), Some(value)) = (iter.next(), iter.next()) {
+                    map.insert(key.parse()?, value.parse()?);
+                }
+                Ok(map)
+            }
+            ReplyType::Null => Ok(HashMap::new()),
+            _ => Err(Error::new("Reply type is not map")),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -172,4 +583,11 @@ pub enum ReplyType {
     Integer = raw::REDISMODULE_REPLY_INTEGER as isize,
     Array = raw::REDISMODULE_REPLY_ARRAY as isize,
     Null = raw::REDISMODULE_REPLY_NULL as isize,
+    Map = raw::REDISMODULE_REPLY_MAP as isize,
+    Set = raw::REDISMODULE_REPLY_SET as isize,
+    Bool = raw::REDISMODULE_REPLY_BOOL as isize,
+    Double = raw::REDISMODULE_REPLY_DOUBLE as isize,
+    BigNumber = raw::REDISMODULE_REPLY_BIG_NUMBER as isize,
+    Verbatim = raw::REDISMODULE_REPLY_VERBATIM_STRING as isize,
+    Attribute = raw::REDISMODULE_REPLY_ATTRIBUTE as isize,
 }
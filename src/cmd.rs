@@ -0,0 +1,86 @@
+//! Build a command for `Context::call` and `Context::replicate`
+
+use crate::call_arg::{CallArg, ToCallArg};
+use crate::call_reply::{CallReply, FromCallReply};
+use crate::context::Context;
+use crate::error::Error;
+use crate::CallFlag;
+
+/// A command builder, the same command can be called or replicated.
+///
+/// ```rust,no_run
+/// let added: i64 = Cmd::new("ZADD")
+///     .arg(key)
+///     .arg_if(nx, "NX")
+///     .args(&pairs)
+///     .query(ctx)?;
+/// ```
+pub struct Cmd<'a> {
+    name: String,
+    args: Vec<CallArg<'a>>,
+    flags: Option<CallFlag>,
+}
+
+impl<'a> Cmd<'a> {
+    /// Create a command with no arguments
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        Cmd {
+            name: name.into(),
+            args: vec![],
+            flags: None,
+        }
+    }
+    /// Append an argument
+    pub fn arg<T: ToCallArg + ?Sized>(mut self, arg: &'a T) -> Self {
+        self.args.push(arg.to_call_arg());
+        self
+    }
+    /// Append an argument only if `cond` is true, useful for optional
+    /// keywords like `NX` or `WITHSCORES`
+    pub fn arg_if<T: ToCallArg + ?Sized>(self, cond: bool, arg: &'a T) -> Self {
+        if cond {
+            self.arg(arg)
+        } else {
+            self
+        }
+    }
+    /// Append all the arguments of a iterator
+    pub fn args<I, T>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = &'a T>,
+        T: ToCallArg + ?Sized + 'a,
+    {
+        self.args.extend(args.into_iter().map(|v| v.to_call_arg()));
+        self
+    }
+    /// Set the flags used when calling or replicating the command
+    pub fn flags(mut self, flags: CallFlag) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+    /// Return the name of the command
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    /// Return the number of arguments, the command name is not included
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+    /// Return true if the command has no arguments
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+    /// Call the command and return the raw reply, error replies are not
+    /// converted to `Err`.
+    pub fn exec(&self, ctx: &mut Context) -> Result<CallReply, Error> {
+        ctx.call(&self.name, self.flags, &self.args)
+    }
+    /// Call the command and convert the reply, error replies are returned as `Err`.
+    pub fn query<T: FromCallReply>(&self, ctx: &mut Context) -> Result<T, Error> {
+        self.exec(ctx)?.parse()
+    }
+    /// Replicate the command to replicas and AOF, see `Context::replicate`
+    pub fn replicate(&self, ctx: &Context) -> Result<(), Error> {
+        ctx.replicate(&self.name, self.flags, &self.args)
+    }
+}
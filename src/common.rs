@@ -145,23 +145,93 @@ impl Into<CString> for LogLevel {
     }
 }
 
-/// Controls the Whether replicate the command
-pub enum CallFlag {
-    /// Tells the function to replicate the command to replicas and AOF
-    AofAndReplicas,
-    /// Tells the function to replicate the command to AOF only
-    Aof,
-    /// Tells the function to replicate the command to replicas
-    Replicas,
+/// Flags of `Context::call` and `Context::replicate`, combine them with `|`
+///
+/// ```rust,no_run
+/// let flags = CallFlag::REPLICATE | CallFlag::NO_AOF | CallFlag::CHECK_ACL;
+/// ctx.call("INCR", Some(flags), &["foo"])?;
+/// ```
+///
+/// Flags that the running server does not support make the call fail instead
+/// of being ignored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CallFlag(u32);
+
+/// The fmt char and the minimal server version (0x00MMmmpp) of each flag
+const CALL_FLAGS: [(CallFlag, char, i32); 10] = [
+    (CallFlag::REPLICATE, '!', 0x00_04_00_00),
+    (CallFlag::NO_AOF, 'A', 0x00_04_00_00),
+    (CallFlag::NO_REPLICAS, 'R', 0x00_04_00_00),
+    (CallFlag::CHECK_ACL, 'C', 0x00_07_00_00),
+    (CallFlag::SCRIPT_MODE, 'S', 0x00_07_00_00),
+    (CallFlag::DENY_WRITE, 'W', 0x00_07_00_00),
+    (CallFlag::RESPECT_OOM, 'M', 0x00_07_00_00),
+    (CallFlag::RESP3, '3', 0x00_07_00_00),
+    (CallFlag::RESP_AUTO, '0', 0x00_07_00_00),
+    (CallFlag::ERROR_AS_REPLY, 'E', 0x00_07_00_00),
+];
+
+impl CallFlag {
+    /// No flag
+    pub const NONE: CallFlag = CallFlag(0);
+    /// Replicate the command to replicas and AOF
+    pub const REPLICATE: CallFlag = CallFlag(1 << 0);
+    /// Do not replicate the command to AOF, used with `CallFlag::REPLICATE`
+    pub const NO_AOF: CallFlag = CallFlag(1 << 1);
+    /// Do not replicate the command to replicas, used with `CallFlag::REPLICATE`
+    pub const NO_REPLICAS: CallFlag = CallFlag(1 << 2);
+    /// Check the command against the ACL rules of the user attached to the context
+    pub const CHECK_ACL: CallFlag = CallFlag(1 << 3);
+    /// Run the command like a script does, which rejects the commands not allowed
+    /// in scripts and the writes while the server is out of memory or read only
+    pub const SCRIPT_MODE: CallFlag = CallFlag(1 << 4);
+    /// Reject write commands
+    pub const DENY_WRITE: CallFlag = CallFlag(1 << 5);
+    /// Reject commands flagged deny-oom while the server is out of memory
+    pub const RESPECT_OOM: CallFlag = CallFlag(1 << 6);
+    /// Get the reply in RESP3
+    pub const RESP3: CallFlag = CallFlag(1 << 7);
+    /// Get the reply in the protocol of the client attached to the context
+    pub const RESP_AUTO: CallFlag = CallFlag(1 << 8);
+    /// Return the errors of the call as error replies instead of failing
+    pub const ERROR_AS_REPLY: CallFlag = CallFlag(1 << 9);
+
+    /// Whether all the flags in `other` are set
+    pub fn contains(&self, other: CallFlag) -> bool {
+        self.0 & other.0 == other.0
+    }
+    /// The minimal server version (0x00MMmmpp) supporting all the flags
+    pub fn min_version(&self) -> i32 {
+        CALL_FLAGS
+            .iter()
+            .filter(|(flag, _, _)| self.contains(*flag))
+            .map(|(_, _, version)| *version)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl std::ops::BitOr for CallFlag {
+    type Output = CallFlag;
+    fn bitor(self, rhs: CallFlag) -> CallFlag {
+        CallFlag(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for CallFlag {
+    fn bitor_assign(&mut self, rhs: CallFlag) {
+        self.0 |= rhs.0
+    }
 }
 
 impl Into<CString> for CallFlag {
     fn into(self) -> CString {
-        match self {
-            CallFlag::AofAndReplicas => CString::new("v!").unwrap(),
-            CallFlag::Aof => CString::new("vR").unwrap(),
-            CallFlag::Replicas => CString::new("vA").unwrap(),
-        }
+        let mut fmt = String::from("v");
+        CALL_FLAGS
+            .iter()
+            .filter(|(flag, _, _)| self.contains(*flag))
+            .for_each(|(_, c, _)| fmt.push(*c));
+        CString::new(fmt).unwrap()
     }
 }
 
@@ -0,0 +1,80 @@
+use super::Context;
+use crate::error::Error;
+use crate::key::{ReadKey, WriteKey};
+use crate::string::RStr;
+
+use std::ops::{Deref, DerefMut};
+
+/// Restores the previously selected DB when dropped
+///
+/// create with [`ctx.select_db_scoped`](./struct.Context.html#method.select_db_scoped)
+pub struct DbGuard<'a> {
+    ctx: &'a mut Context,
+    prev_db: i32,
+}
+
+impl<'a> Deref for DbGuard<'a> {
+    type Target = Context;
+    fn deref(&self) -> &Self::Target {
+        self.ctx
+    }
+}
+
+impl<'a> DerefMut for DbGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.ctx
+    }
+}
+
+impl<'a> Drop for DbGuard<'a> {
+    fn drop(&mut self) {
+        let _ = self.ctx.select_db(self.prev_db);
+    }
+}
+
+impl Context {
+    /// Change the currently selected DB like `Context::select_db`, the previous
+    /// DB is selected again when the returned guard is dropped.
+    ///
+    /// ```rust,no_run
+    /// {
+    ///     let mut ctx = ctx.select_db_scoped(1)?;
+    ///     ctx.open_write_key(&args[1]).string_set(&args[2])?;
+    /// }
+    /// ```
+    pub fn select_db_scoped(&mut self, db: i32) -> Result<DbGuard<'_>, Error> {
+        let prev_db = self.get_select_db() as i32;
+        self.select_db(db)?;
+        Ok(DbGuard { ctx: self, prev_db })
+    }
+    /// Call `f` with the DB `db` selected, and select the previous DB again
+    /// before returning, even if `f` fails.
+    pub fn with_db<F, R>(&mut self, db: i32, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut Context) -> Result<R, Error>,
+    {
+        let mut guard = self.select_db_scoped(db)?;
+        f(&mut guard)
+    }
+    /// Like `Context::open_read_key`, but the key is looked up in the DB `db`.
+    ///
+    /// The selected DB is left unchanged, the key keeps refering to `db`.
+    pub fn open_read_key_in_db(&self, db: i32, keyname: &RStr) -> Result<ReadKey<'_>, Error> {
+        self.in_db(db, || ReadKey::new(self, keyname))
+    }
+    /// Like `Context::open_write_key`, but the key is looked up in the DB `db`.
+    ///
+    /// The selected DB is left unchanged, the key keeps refering to `db`.
+    pub fn open_write_key_in_db(&mut self, db: i32, keyname: &RStr) -> Result<WriteKey<'_>, Error> {
+        let ctx: &Context = self;
+        ctx.in_db(db, || WriteKey::open(ctx, keyname))
+    }
+    /// Call `f` with the DB `db` selected, without borrowing the context mutably
+    pub(crate) fn in_db<F: FnOnce() -> R, R>(&self, db: i32, f: F) -> Result<R, Error> {
+        let prev_db = self.get_select_db() as i32;
+        self.select_db(db)?;
+        let result = f();
+        self.select_db(prev_db)?;
+        Ok(result)
+    }
+}
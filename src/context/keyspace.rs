@@ -0,0 +1,103 @@
+use super::Context;
+use crate::call_reply::{CallReply, ReplyType};
+use crate::error::Error;
+use crate::key::{KeyType, ReadKey};
+use crate::raw;
+use crate::string::{RStr, RString};
+use crate::{FromPtr, GetPtr};
+
+use std::os::raw::c_int;
+
+impl Context {
+    /// Return the name of a random key in the currently selected DB, or None
+    /// if the DB is empty.
+    pub fn random_key(&self) -> Option<RString> {
+        let ptr = unsafe { raw::RedisModule_RandomKey.unwrap()(self.ptr) };
+        if ptr.is_null() {
+            None
+        } else {
+            Some(RString::from_ptr(ptr))
+        }
+    }
+    /// Check whether the key exists, without updating its last access time.
+    pub fn key_exists(&self, keyname: &RStr) -> bool {
+        let mode = (raw::REDISMODULE_READ | raw::REDISMODULE_OPEN_KEY_NOTOUCH) as c_int;
+        !ReadKey::open(self, keyname, mode).is_empty()
+    }
+    /// Copy the value of the key `src` to the key `dest` with its expire, like the
+    /// COPY command.
+    ///
+    /// The bool indicate whether the value was copied, it is not when `src` does not
+    /// exist, or when `dest` exists and `replace` is false.
+    ///
+    /// Since Redis 6.2, the key is copied with COPY, which copies module types with the
+    /// `copy` method of the type and fires the `copy_to` event on `dest`. The module
+    /// types without `copy` method can't be copied. Before Redis 6.2, native types are
+    /// copied with DUMP and RESTORE, which fires the `restore` event on `dest`, and
+    /// module types can't be copied.
+    ///
+    /// Nothing is replicated, the command should use `Context::replicate_verbatim`.
+    pub fn copy_key(&mut self, src: &RStr, dest: &RStr, replace: bool) -> Result<bool, Error> {
+        if src.get_buffer() == dest.get_buffer() {
+            return Err(Error::new(
+                "ERR source and destination objects are the same",
+            ));
+        }
+        let replace_arg = RString::from_str("REPLACE");
+        if self.get_server_version()? >= 0x00_06_02_00 {
+            let mut args = vec![src, dest];
+            if replace {
+                args.push(&replace_arg);
+            }
+            let copied = self.call_checked("COPY", &args)?.get_integer()?;
+            return Ok(copied == 1);
+        }
+        let src_key = self.open_read_key(src);
+        if src_key.is_empty() || (!replace && self.key_exists(dest)) {
+            return Ok(false);
+        }
+        if src_key.get_type() == KeyType::Module {
+            return Err(Error::new(
+                "ERR copying a module type needs Redis 6.2 or later",
+            ));
+        }
+        let ttl = match src_key.get_expire() {
+            Some(v) => v.as_millis().max(1),
+            None => 0,
+        };
+        drop(src_key);
+        let dump = self.call_checked("DUMP", &[src])?;
+        let payload =
+            unsafe { raw::RedisModule_CreateStringFromCallReply.unwrap()(dump.get_ptr()) };
+        if payload.is_null() {
+            return Err(Error::new("fail to dump key"));
+        }
+        let payload = RString::from_ptr(payload);
+        let ttl = RString::from_str(ttl.to_string());
+        let mut args = vec![dest, &ttl, &payload];
+        if replace {
+            args.push(&replace_arg);
+        }
+        self.call_checked("RESTORE", &args)?;
+        Ok(true)
+    }
+    /// Rename the key `src` to `dest` with its expire, like the RENAME command.
+    ///
+    /// The key is renamed with RENAME, which moves the value of module types as it is
+    /// and fires the `rename_from` and `rename_to` events.
+    ///
+    /// Nothing is replicated, the command should use `Context::replicate_verbatim`.
+    pub fn rename_key(&mut self, src: &RStr, dest: &RStr) -> Result<(), Error> {
+        self.call_checked("RENAME", &[src, dest])?;
+        Ok(())
+    }
+
+    /// Call a command, error replies are turned into errors.
+    fn call_checked(&mut self, command: &str, args: &[&RStr]) -> Result<CallReply, Error> {
+        let reply = self.call(command, None, args)?;
+        if reply.get_type() == ReplyType::Error {
+            return Err(Error::new(reply.get_string()?));
+        }
+        Ok(reply)
+    }
+}
@@ -1,27 +1,54 @@
 //! Module context
 
+use crate::call_arg::{CallArg, ToCallArg};
 use crate::call_reply::CallReply;
-use crate::error::Error;
+use crate::error::{CallError, Error};
 use crate::key::{ReadKey, WriteKey};
 use crate::raw;
+use crate::rtype::{AuxData, AuxGuard, AuxState};
 use crate::scan_cursor::ScanCursor;
 use crate::string::{RStr, RString};
 use crate::user::User;
 use crate::value::Value;
 use crate::{handle_status, CallFlag, FromPtr, GetPtr, LogLevel, RResult, ServerEvent};
 
+use std::cell::{Cell, RefCell};
 use std::ffi::CString;
-use std::os::raw::{c_char, c_int, c_long, c_void};
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Mutex;
 
 mod block_client;
 mod cluster;
+mod db;
+mod keyspace;
+mod reply;
 mod timer;
 
+pub use db::DbGuard;
+
+/// Options set by `Context::set_module_options`
+static MODULE_OPTIONS: AtomicI32 = AtomicI32::new(0);
+
+/// Cached result of `Context::get_server_version`, 0 until it is known
+static SERVER_VERSION: AtomicI32 = AtomicI32::new(0);
+
+/// Whether Redis signals the keys opened for writing when they are closed, which
+/// is the case unless the module set REDISMODULE_OPTION_NO_IMPLICIT_SIGNAL_MODIFIED
+pub(crate) fn is_implicit_signal_modified() -> bool {
+    let options = MODULE_OPTIONS.load(Ordering::Relaxed);
+    options & raw::REDISMODULE_OPTION_NO_IMPLICIT_SIGNAL_MODIFIED as i32 == 0
+}
+
 /// Wrap raw pointer `raw::RedisModuleCtx`
-#[repr(C)]
 pub struct Context {
     ptr: *mut raw::RedisModuleCtx,
+    /// Set by `Context::set_replicate_effects`
+    replicate_effects: Cell<bool>,
+    /// Commands passed to `Context::replicate` in effects mode
+    replicated: RefCell<Vec<(CString, CString, Vec<RString>)>>,
+    /// Whether the running command has the "random" flag
+    random_command: Cell<bool>,
 }
 
 impl GetPtr for Context {
@@ -34,7 +61,12 @@ impl GetPtr for Context {
 impl FromPtr for Context {
     type PtrType = raw::RedisModuleCtx;
     fn from_ptr(ptr: *mut raw::RedisModuleCtx) -> Context {
-        Context { ptr }
+        Context {
+            ptr,
+            replicate_effects: Cell::new(false),
+            replicated: RefCell::new(Vec::new()),
+            random_command: Cell::new(false),
+        }
     }
 }
 
@@ -71,60 +103,61 @@ impl Context {
     /// Send reply to client
     ///
     /// It will choose the correct redis ffi function to reply depends on the `result::RResult` value.
+    /// To reply without building a `Value`, see `Context::reply_integer` and the
+    /// other direct reply methods.
     pub fn reply(&self, r: RResult) {
         match r {
-            Ok(Value::Integer(v)) => unsafe {
-                raw::RedisModule_ReplyWithLongLong.unwrap()(self.ptr, v);
-            },
-            Ok(Value::Double(v)) => unsafe {
-                raw::RedisModule_ReplyWithDouble.unwrap()(self.ptr, v);
-            },
-            Ok(Value::String(v)) => unsafe {
-                let msg = CString::new(v).unwrap();
-                raw::RedisModule_ReplyWithSimpleString.unwrap()(self.ptr, msg.as_ptr());
-            },
-            Ok(Value::BulkString(v)) => unsafe {
-                raw::RedisModule_ReplyWithStringBuffer.unwrap()(
-                    self.ptr,
-                    v.as_ptr() as *const c_char,
-                    v.len(),
-                );
-            },
+            Ok(Value::Integer(v)) => self.reply_integer(v),
+            Ok(Value::Double(v)) => self.reply_double(v),
+            Ok(Value::BigNumber(v)) => self.reply_big_number(v),
+            Ok(Value::String(v)) => self.reply_simple(v),
+            Ok(Value::BulkString(v)) => self.reply_bulk(v),
             Ok(Value::Array(v)) => {
-                unsafe {
-                    raw::RedisModule_ReplyWithArray.unwrap()(self.ptr, v.len() as c_long);
-                }
+                self.reply_array(v.len());
                 v.into_iter().for_each(|elem| self.reply(Ok(elem)));
             }
-            Ok(Value::Null) => unsafe {
-                raw::RedisModule_ReplyWithNull.unwrap()(self.ptr);
-            },
+            Ok(Value::Null) => self.reply_null(),
+            Ok(Value::NullArray) => self.reply_null_array(),
+            Ok(Value::Error(v)) => self.reply_error(v),
             Ok(Value::NoReply) => {}
-            Err(Error::WrongArity) => unsafe {
-                raw::RedisModule_WrongArity.unwrap()(self.ptr);
-            },
-            Err(err) => unsafe {
-                let msg = CString::new(err.to_string()).unwrap();
-                raw::RedisModule_ReplyWithError.unwrap()(self.ptr, msg.as_ptr());
-            },
+            Err(Error::WrongArity) => self.reply_wrong_arity(),
+            Err(err) => self.reply_error(err.to_string()),
         }
     }
     /// Exported API to call any Redis command from modules.
-    pub fn call<T: AsRef<str>>(
+    ///
+    /// The arguments can be any `ToCallArg`, use the [`call!`](../macro.call.html)
+    /// macro to mix arguments of different types.
+    ///
+    /// The command may change the keyspace, so the context is borrowed mutably and
+    /// no key can be open during the call.
+    pub fn call<T: AsRef<str>, A: ToCallArg>(
+        &mut self,
+        command: T,
+        flags: Option<CallFlag>,
+        args: &[A],
+    ) -> Result<CallReply, Error> {
+        self.call_unchecked(command, flags, args)
+    }
+    /// Like `Context::call`, for the commands which don't change the keyspace,
+    /// like `INFO`, or when no key is open.
+    pub(crate) fn call_unchecked<T: AsRef<str>, A: ToCallArg>(
         &self,
         command: T,
         flags: Option<CallFlag>,
-        args: &[T],
+        args: &[A],
     ) -> Result<CallReply, Error> {
-        let str_args: Vec<RString> = args.iter().map(|v| RString::from_str(v.as_ref())).collect();
+        let call_args: Vec<CallArg> = args.iter().map(|v| v.to_call_arg()).collect();
         let args: Vec<*mut raw::RedisModuleString> =
-            str_args.iter().map(|v| v.get_rstr().get_ptr()).collect();
+            call_args.iter().map(|v| v.get_ptr()).collect();
 
         let cmd = CString::new(command.as_ref()).unwrap();
-        let flags: CString = match flags {
-            Some(v) => v.into(),
-            None => CString::new("v").unwrap(),
+        let flags = if self.replicate_effects.get() {
+            Some(flags.unwrap_or_default() | CallFlag::REPLICATE)
+        } else {
+            flags
         };
+        let flags = self.call_flag_fmt(flags)?;
 
         let reply: *mut raw::RedisModuleCallReply = unsafe {
             raw::RedisModule_Call.unwrap()(
@@ -136,10 +169,12 @@ impl Context {
             )
         };
         if reply.is_null() {
-            Err(Error::new(format!(
-                "fail to call command {}",
-                command.as_ref()
-            )))
+            // read errno before anything else can allocate and overwrite it
+            let error = CallError::last();
+            Err(Error::Call {
+                command: command.as_ref().to_owned(),
+                error,
+            })
         } else {
             Ok(CallReply::from_ptr(reply))
         }
@@ -178,26 +213,35 @@ impl Context {
     /// and the command specified is inserted in the AOF and replication stream
     /// immediately.
     ///
+    /// ## Note about the effects mode:
+    ///
+    /// When the effects are replicated, see `Context::set_replicate_effects`, the
+    /// commands are collected and replicated when the module command returns, after
+    /// the effects of `Context::call`, whatever the Redis version.
+    ///
     /// ## Return value
     ///
     /// The command returns Err if the format specifiers are invalid
-    /// or the command name does not belong to a known command.
-    pub fn replicate<T: AsRef<str>>(
+    /// or the command name does not belong to a known command. The command name
+    /// of a collected command is checked when it is replicated.
+    pub fn replicate<T: AsRef<str>, A: ToCallArg>(
         &self,
         command: T,
         flags: Option<CallFlag>,
-        args: &[T],
+        args: &[A],
     ) -> Result<(), Error> {
-        let str_args: Vec<RString> = args.iter().map(|v| RString::from_str(v.as_ref())).collect();
-        let args: Vec<*mut raw::RedisModuleString> =
-            str_args.iter().map(|v| v.get_rstr().get_ptr()).collect();
-
         let cmd = CString::new(command.as_ref()).unwrap();
-        let flags: CString = match flags {
-            Some(v) => v.into(),
-            None => CString::new("v").unwrap(),
-        };
-
+        let flags = self.call_flag_fmt(flags)?;
+        if self.replicate_effects.get() {
+            let args = args.iter().map(|v| v.to_call_arg().into_owned()).collect();
+            self.replicated.borrow_mut().push((cmd, flags, args));
+            return Ok(());
+        }
+        let call_args: Vec<CallArg> = args.iter().map(|v| v.to_call_arg()).collect();
+        self.replicate_raw(&cmd, &flags, &call_args)
+    }
+    fn replicate_raw(&self, cmd: &CString, flags: &CString, args: &[CallArg]) -> Result<(), Error> {
+        let args: Vec<*mut raw::RedisModuleString> = args.iter().map(|v| v.get_ptr()).collect();
         let result = unsafe {
             let p_call = raw::RedisModule_Replicate.unwrap();
             p_call(
@@ -208,7 +252,22 @@ impl Context {
                 args.len(),
             )
         };
-        handle_status(result, format!("fail to replicate {}", command.as_ref()))
+        handle_status(
+            result,
+            format!("fail to replicate {}", cmd.to_string_lossy()),
+        )
+    }
+    /// Replicate the commands collected by `Context::replicate` in effects mode,
+    /// called by the `rcmd` wrapper when the command returns.
+    #[doc(hidden)]
+    pub fn flush_replicated(&self) -> Result<(), Error> {
+        let replicated = std::mem::take(&mut *self.replicated.borrow_mut());
+        let mut result = Ok(());
+        for (cmd, flags, args) in replicated {
+            let args: Vec<CallArg> = args.into_iter().map(CallArg::Owned).collect();
+            result = result.and(self.replicate_raw(&cmd, &flags, &args));
+        }
+        result
     }
     /// This function will replicate the command exactly as it was invoked
     /// by the client. Note that this function will not wrap the command into
@@ -219,10 +278,58 @@ impl Context {
     /// the command to the slaves and AOF file exactly as it was called, since
     /// the command can just be re-executed to deterministically re-create the
     /// new state starting from the old one.
-    pub fn replicate_verbatim(&self) {
+    ///
+    /// A command with the "random" flag should not be replicated verbatim since
+    /// replicas would not get the same result, a warning is logged in this case.
+    /// When the effects are replicated, see `Context::set_replicate_effects`,
+    /// nothing is replicated and an error is returned.
+    pub fn replicate_verbatim(&self) -> Result<(), Error> {
+        if self.replicate_effects.get() {
+            return Err(Error::new(
+                "replicate_verbatim is called by a command which replicates its effects",
+            ));
+        }
+        if self.random_command.get() {
+            self.warning(
+                "replicate_verbatim is called by a command which is random, \
+                 replicas may be inconsistent",
+            );
+        }
         unsafe {
             raw::RedisModule_ReplicateVerbatim.unwrap()(self.ptr);
         }
+        Ok(())
+    }
+    /// Replicate the effects of the running command instead of the command itself,
+    /// which is needed by commands using randomness or time.
+    ///
+    /// While enabled, every command executed with `Context::call` gets the
+    /// `CallFlag::REPLICATE` flag, so the writes it performs are replicated.
+    /// The commands of `Context::replicate` are collected and replicated when the
+    /// command returns, and `Context::replicate_verbatim` fails.
+    ///
+    /// The mode lasts for the running command, it can also be enabled with the
+    /// `replicate_effects` attribute of `rcmd`:
+    /// ```rust,no_run
+    /// #[rcmd("hello.rand.set", "write random", replicate_effects)]
+    /// fn hello_rand_set(ctx: &mut Context, args: Vec<RStr>) -> RResult {
+    ///     call!(ctx, "SET", &args[1], random::<i64>())?;
+    ///     Ok("OK".into())
+    /// }
+    /// ```
+    pub fn set_replicate_effects(&self, enable: bool) {
+        self.replicate_effects.set(enable);
+    }
+    /// Whether the effects of the running command are replicated,
+    /// see `Context::set_replicate_effects`
+    pub fn is_replicate_effects(&self) -> bool {
+        self.replicate_effects.get()
+    }
+    /// Mark the running command as declared with the "random" flag, called by
+    /// the `rcmd` wrapper.
+    #[doc(hidden)]
+    pub fn set_random_command(&self) {
+        self.random_command.set(true);
     }
     /// Return the ID of the current client calling the currently active module
     /// command. The returned ID has a few guarantees:
@@ -238,6 +345,50 @@ impl Context {
     pub fn get_client_id(&self) -> u64 {
         unsafe { raw::RedisModule_GetClientId.unwrap()(self.ptr) as u64 }
     }
+    /// Return the version of the running server as 0x00MMmmpp, e.g. 0x00060009
+    /// for 6.0.9.
+    ///
+    /// The version is given by `RedisModule_GetServerVersion` since Redis 6.0.9.
+    /// On older servers it is parsed from `INFO server` the first time, then cached.
+    pub fn get_server_version(&self) -> Result<i32, Error> {
+        if let Some(get_server_version) = unsafe { raw::RedisModule_GetServerVersion } {
+            return Ok(unsafe { get_server_version() });
+        }
+        let version = SERVER_VERSION.load(Ordering::Relaxed);
+        if version != 0 {
+            return Ok(version);
+        }
+        let info = self
+            .call_unchecked("INFO", None, &["server"])?
+            .get_string()?;
+        let version = info
+            .lines()
+            .find_map(|line| line.strip_prefix("redis_version:"))
+            .ok_or_else(|| Error::new("fail to get server version"))?
+            .trim()
+            .split('.')
+            .take(3)
+            .try_fold(0, |acc, v| v.parse::<i32>().map(|v| (acc << 8) | v))?;
+        SERVER_VERSION.store(version, Ordering::Relaxed);
+        Ok(version)
+    }
+    /// Serialize the flags of `Context::call` and `Context::replicate`, failing
+    /// if the server does not support some of them.
+    fn call_flag_fmt(&self, flags: Option<CallFlag>) -> Result<CString, Error> {
+        let flags = flags.unwrap_or_default();
+        let min_version = flags.min_version();
+        let fmt: CString = flags.into();
+        if min_version > 0x00_04_00_00 && self.get_server_version()? < min_version {
+            return Err(Error::new(format!(
+                "call flags '{}' need Redis {}.{}.{} or later",
+                fmt.to_string_lossy(),
+                min_version >> 16,
+                (min_version >> 8) & 0xff,
+                min_version & 0xff
+            )));
+        }
+        Ok(fmt)
+    }
     /// Return the currently selected DB
     pub fn get_select_db(&self) -> i64 {
         unsafe { raw::RedisModule_GetSelectedDb.unwrap()(self.ptr) as i64 }
@@ -313,6 +464,7 @@ impl Context {
     /// If the module command wishes to change something in a different DB and
     /// returns back to the original one, it should call `Context::get_selected_db`
     /// before in order to restore the old DB number before returning.
+    /// `Context::select_db_scoped` and `Context::with_db` do that automatically.
     pub fn select_db(&self, newid: i32) -> Result<(), Error> {
         handle_status(
             unsafe { raw::RedisModule_SelectDb.unwrap()(self.ptr, newid) },
@@ -324,14 +476,42 @@ impl Context {
     /// as argument to perform operations on the key.
     ///
     /// Note The key may be not existed.
-    pub fn open_read_key(&self, keyname: &RStr) -> ReadKey {
-        ReadKey::new(self.ptr, keyname)
+    ///
+    /// While the key is open, the context can't be used mutably, so the value
+    /// borrowed from the key can't be changed by `Context::call` for example.
+    pub fn open_read_key(&self, keyname: &RStr) -> ReadKey<'_> {
+        ReadKey::new(self, keyname)
     }
     /// Return an handle representing a Redis key with write permission only,
     /// so that it is possible, to call other APIs with the key handle
     /// as argument to perform operations on the key.
-    pub fn open_write_key(&self, keyname: &RStr) -> WriteKey {
-        WriteKey::new(self.ptr, keyname)
+    ///
+    /// The key borrows the context mutably, so no other key can be opened while it
+    /// is open, and the value of the key can't be borrowed by two handles.
+    pub fn open_write_key(&mut self, keyname: &RStr) -> WriteKey<'_> {
+        WriteKey::new(self, keyname)
+    }
+    /// Like `Context::open_write_key`, but several keys are opened at once.
+    ///
+    /// The names must be distinct, so the values of the keys can be borrowed
+    /// mutably at the same time.
+    /// ```rust,no_run
+    /// let [mut src, mut dest] = ctx.open_write_keys([&args[1], &args[2]])?;
+    /// ```
+    pub fn open_write_keys<const N: usize>(
+        &mut self,
+        keynames: [&RStr; N],
+    ) -> Result<[WriteKey<'_>; N], Error> {
+        for (i, name) in keynames.iter().enumerate() {
+            if keynames[..i]
+                .iter()
+                .any(|v| v.get_buffer() == name.get_buffer())
+            {
+                return Err(Error::new("ERR the same key is opened twice"));
+            }
+        }
+        let ctx: &Context = self;
+        Ok(keynames.map(|name| WriteKey::open(ctx, name)))
     }
     /// This function is used in order to potentially unblock a client blocked
     /// on keys with `Context::block_client_on_keys`. When this function is called,
@@ -518,7 +698,13 @@ impl Context {
     /// The module should use RedisModule_IsIOError after reads, before using the
     /// data that was read, and in case of error, propagate it upwards, and also be
     /// able to release the partially populated value and all it's allocations.
+    ///
+    /// REDISMODULE_OPTION_NO_IMPLICIT_SIGNAL_MODIFIED:
+    /// The module does not want Redis to signal every key opened for writing when it
+    /// is closed. `WriteKey` then signals the keys which were modified, the other
+    /// changes must be signaled with `Context::signal_modified_key`.
     pub fn set_module_options(&self, options: i32) {
+        MODULE_OPTIONS.store(options, Ordering::Relaxed);
         unsafe { raw::RedisModule_SetModuleOptions.unwrap()(self.ptr, options) }
     }
     /// Scan API that allows a module to scan all the keys and value in
@@ -914,6 +1100,13 @@ impl Context {
             "fail to notify keyspace event",
         )
     }
+
+    /// Borrow a module global value persisted by the aux callbacks of a data type.
+    ///
+    /// Return an error if the value is already borrowed, by this thread or another one.
+    pub fn aux<'a, T: AuxData>(&'a self, state: &'a AuxState<T>) -> Result<AuxGuard<'a, T>, Error> {
+        state.try_borrow()
+    }
 }
 
 /// A implemention of thread safe context
@@ -0,0 +1,143 @@
+use super::Context;
+use crate::raw;
+use crate::resp;
+use crate::string::RStr;
+use crate::GetPtr;
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_long};
+
+/// Strings shorter than this are NUL terminated on the stack
+const STACK_CSTR_LEN: usize = 128;
+
+/// Call `f` with a NUL terminated copy of `s`, short strings are not allocated.
+///
+/// Panics if `s` contains a NUL byte.
+fn with_cstr<F: FnOnce(*const c_char)>(s: &str, f: F) {
+    let bytes = s.as_bytes();
+    if bytes.len() < STACK_CSTR_LEN && !bytes.contains(&0) {
+        let mut buf = [0u8; STACK_CSTR_LEN];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        f(buf.as_ptr() as *const c_char)
+    } else {
+        let s = CString::new(s).unwrap();
+        f(s.as_ptr())
+    }
+}
+
+/// Reply to the client directly, without building a `Value`.
+///
+/// A command replying with these methods must return `Ok(Value::NoReply)`,
+/// so nothing more is replied by the `rcmd` wrapper.
+/// ```rust,no_run
+/// #[rcmd("hello.pair")]
+/// fn hello_pair(ctx: &mut Context, args: Vec<RStr>) -> RResult {
+///     ctx.reply_array(2);
+///     ctx.reply_string(&args[1]);
+///     ctx.reply_integer(args.len() as i64);
+///     Ok(Value::NoReply)
+/// }
+/// ```
+impl Context {
+    /// Reply with an integer
+    pub fn reply_integer(&self, v: i64) {
+        unsafe {
+            raw::RedisModule_ReplyWithLongLong.unwrap()(self.ptr, v);
+        }
+    }
+    /// Reply with a double, as a bulk string in RESP2
+    pub fn reply_double(&self, v: f64) {
+        unsafe {
+            raw::RedisModule_ReplyWithDouble.unwrap()(self.ptr, v);
+        }
+    }
+    /// Reply with the decimal digits of a big number, as a bulk string in RESP2 or
+    /// before Redis 7.0
+    pub fn reply_big_number<T: AsRef<str>>(&self, v: T) {
+        let v = v.as_ref();
+        match unsafe { raw::RedisModule_ReplyWithBigNumber } {
+            Some(reply_big_number) => unsafe {
+                reply_big_number(self.ptr, v.as_ptr() as *const c_char, v.len());
+            },
+            None => self.reply_bulk(v),
+        }
+    }
+    /// Reply with a simple string.
+    ///
+    /// A string holding "\r", "\n" or NUL can't be a simple string, it is replied
+    /// as a bulk string instead.
+    pub fn reply_simple<T: AsRef<str>>(&self, v: T) {
+        let v = v.as_ref();
+        if !resp::is_simple(v) {
+            return self.reply_bulk(v);
+        }
+        with_cstr(v, |msg| unsafe {
+            raw::RedisModule_ReplyWithSimpleString.unwrap()(self.ptr, msg);
+        });
+    }
+    /// Reply with a binary safe bulk string
+    pub fn reply_bulk<T: AsRef<[u8]>>(&self, v: T) {
+        let v = v.as_ref();
+        unsafe {
+            raw::RedisModule_ReplyWithStringBuffer.unwrap()(
+                self.ptr,
+                v.as_ptr() as *const c_char,
+                v.len(),
+            );
+        }
+    }
+    /// Reply with a redis string as a bulk string, without copying it
+    pub fn reply_string(&self, v: &RStr) {
+        unsafe {
+            raw::RedisModule_ReplyWithString.unwrap()(self.ptr, v.get_ptr());
+        }
+    }
+    /// Reply with the null bulk string
+    pub fn reply_null(&self) {
+        unsafe {
+            raw::RedisModule_ReplyWithNull.unwrap()(self.ptr);
+        }
+    }
+    /// Reply with the null array
+    pub fn reply_null_array(&self) {
+        unsafe {
+            raw::RedisModule_ReplyWithNullArray.unwrap()(self.ptr);
+        }
+    }
+    /// Start an array reply of `len` elements, each of them must be replied next.
+    pub fn reply_array(&self, len: usize) {
+        unsafe {
+            raw::RedisModule_ReplyWithArray.unwrap()(self.ptr, len as c_long);
+        }
+    }
+    /// Start an array reply whose length is not known yet, it must be set with
+    /// `Context::set_array_length` after replying the elements.
+    pub fn reply_postponed_array(&self) {
+        unsafe {
+            raw::RedisModule_ReplyWithArray.unwrap()(
+                self.ptr,
+                raw::REDISMODULE_POSTPONED_ARRAY_LEN as c_long,
+            );
+        }
+    }
+    /// Set the length of the latest array started by `Context::reply_postponed_array`
+    /// and not set yet.
+    pub fn set_array_length(&self, len: usize) {
+        unsafe {
+            raw::RedisModule_ReplySetArrayLength.unwrap()(self.ptr, len as c_long);
+        }
+    }
+    /// Reply with an error. By convention the message starts with an error
+    /// code like "ERR".
+    pub fn reply_error<T: AsRef<str>>(&self, msg: T) {
+        with_cstr(msg.as_ref(), |msg| unsafe {
+            raw::RedisModule_ReplyWithError.unwrap()(self.ptr, msg);
+        });
+    }
+    /// Reply with the error of wrong number of arguments
+    pub fn reply_wrong_arity(&self) {
+        unsafe {
+            raw::RedisModule_WrongArity.unwrap()(self.ptr);
+        }
+    }
+}
@@ -18,7 +18,7 @@ impl Context {
         data: T,
     ) -> Result<raw::RedisModuleTimerID, Error>
     where
-        F: FnOnce(&Context, T),
+        F: FnOnce(&mut Context, T),
     {
         // Store the user-provided data on the heap before passing ownership of it to Redis,
         // so that it will outlive the current scope.
@@ -82,9 +82,9 @@ impl Context {
 
 extern "C" fn timer_proc<F, T>(ctx: *mut raw::RedisModuleCtx, data: *mut c_void)
 where
-    F: FnOnce(&Context, T),
+    F: FnOnce(&mut Context, T),
 {
-    let ctx = &Context::from_ptr(ctx);
+    let ctx = &mut Context::from_ptr(ctx);
     if data.is_null() {
         return;
     }
@@ -93,7 +93,7 @@ where
 }
 
 #[repr(C)]
-struct TimerProcData<F: FnOnce(&Context, T), T> {
+struct TimerProcData<F: FnOnce(&mut Context, T), T> {
     data: T,
     callback: F,
 }
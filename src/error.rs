@@ -11,6 +11,11 @@ use std::fmt::Display;
 pub enum Error {
     WrongArity,
     WrongType,
+    /// `Context::call` failed without running the command
+    Call {
+        command: String,
+        error: CallError,
+    },
     Custom(CustomError),
 }
 
@@ -59,6 +64,10 @@ impl Display for Error {
                 std::str::from_utf8(raw::REDISMODULE_ERRORMSG_WRONGTYPE).unwrap()
             ),
             Error::WrongArity => write!(f, "ERR wrong number of arguments"),
+            Error::Call {
+                ref command,
+                ref error,
+            } => error.fmt_message(f, command),
             Error::Custom(ref err) => write!(f, "{}", err),
         }
     }
@@ -69,10 +78,99 @@ impl error::Error for Error {
         match *self {
             Error::WrongType => None,
             Error::WrongArity => None,
+            Error::Call { .. } => None,
             Error::Custom(ref err) => Some(err),
         }
     }
 }
+/// Why `Context::call` failed, derived from the errno set by `RedisModule_Call`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallError {
+    /// EBADF: wrong format specifier
+    BadFormat,
+    /// EINVAL: wrong command arity
+    WrongArity,
+    /// ENOENT: command does not exist
+    UnknownCommand,
+    /// EPERM: operation in cluster instance with key in non local slot
+    CrossSlot,
+    /// EROFS: operation in cluster instance when a write command is sent
+    /// in a readonly state
+    ReadOnly,
+    /// ENETDOWN: operation in cluster instance when cluster is down
+    ClusterDown,
+    /// ENOTSUP: no ACL user for the specified module context
+    NoUser,
+    /// EACCES: command cannot be executed, according to ACL rules
+    AclDenied,
+    /// ENOSPC: write or deny-oom command is not allowed
+    OutOfMemory,
+    /// ESPIPE: command not allowed on script mode
+    ScriptMode,
+    /// Any other errno
+    Other(i32),
+}
+
+impl CallError {
+    pub fn from_errno(errno: i32) -> CallError {
+        match errno {
+            libc::EBADF => CallError::BadFormat,
+            libc::EINVAL => CallError::WrongArity,
+            libc::ENOENT => CallError::UnknownCommand,
+            libc::EPERM => CallError::CrossSlot,
+            libc::EROFS => CallError::ReadOnly,
+            libc::ENETDOWN => CallError::ClusterDown,
+            libc::ENOTSUP => CallError::NoUser,
+            libc::EACCES => CallError::AclDenied,
+            libc::ENOSPC => CallError::OutOfMemory,
+            libc::ESPIPE => CallError::ScriptMode,
+            _ => CallError::Other(errno),
+        }
+    }
+    /// The errno of the last failed call
+    pub(crate) fn last() -> CallError {
+        let errno = std::io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        CallError::from_errno(errno)
+    }
+    fn fmt_message(&self, f: &mut fmt::Formatter, command: &str) -> fmt::Result {
+        match *self {
+            CallError::BadFormat => write!(f, "ERR invalid arguments format for '{}'", command),
+            CallError::WrongArity => {
+                write!(f, "ERR wrong number of arguments for '{}' command", command)
+            }
+            CallError::UnknownCommand => write!(f, "ERR unknown command '{}'", command),
+            CallError::CrossSlot => write!(
+                f,
+                "ERR '{}' accesses keys in a slot not served by this node",
+                command
+            ),
+            CallError::ReadOnly => {
+                write!(f, "READONLY You can't write against a read only replica.")
+            }
+            CallError::ClusterDown => write!(f, "CLUSTERDOWN The cluster is down"),
+            CallError::NoUser => write!(f, "ERR no user attached to the context for '{}'", command),
+            CallError::AclDenied => write!(
+                f,
+                "NOPERM this user has no permissions to run the '{}' command",
+                command
+            ),
+            CallError::OutOfMemory => {
+                write!(f, "OOM command not allowed when used memory > 'maxmemory'.")
+            }
+            CallError::ScriptMode => {
+                write!(f, "ERR '{}' command is not allowed from scripts", command)
+            }
+            CallError::Other(errno) => {
+                write!(
+                    f,
+                    "ERR fail to call command '{}' (errno {})",
+                    command, errno
+                )
+            }
+        }
+    }
+}
+
 /// A custom eror
 #[derive(Debug)]
 pub struct CustomError {
@@ -1,12 +1,20 @@
 //! Deal with rdb and digest
 
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::ffi::CString;
-use std::os::raw::{c_char, c_uchar};
+use std::ops::Deref;
+use std::os::raw::{c_char, c_uchar, c_ulong, c_void};
 
+use crate::call_arg::{CallArg, ToCallArg};
 use crate::context::Context;
+use crate::error::Error;
 use crate::raw;
 use crate::string::{RStr, RString};
-use crate::{FromPtr, GetPtr, LogLevel};
+use crate::{handle_status, FromPtr, GetPtr, LogLevel};
+
+/// The longest buffer `IO::load_bytes` accepts, it is the default
+/// "proto-max-bulk-len" of Redis.
+pub const MAX_LOAD_BYTES_LEN: usize = 512 * 1024 * 1024;
 
 /// Wrap the pointer of a RedisModuleIO
 #[repr(C)]
@@ -64,20 +72,61 @@ impl IO {
     /// In the context of the rdb_save method of a module type, saves a
     /// string into the RDB file.
     pub fn save_string(&mut self, value: &str) {
-        let value_ = CString::new(value).unwrap();
-        unsafe {
-            raw::RedisModule_SaveStringBuffer.unwrap()(self.ptr, value_.as_ptr(), value.len())
-        }
+        self.save_bytes(value.as_bytes())
     }
     /// In the context of the rdb_load method of a module data type, loads a string
     /// from the RDB file, that was previously saved with `IO::save_string`.
+    ///
+    /// Panics if the load fails or the string is not utf8.
+    #[deprecated(note = "Please use the try_load_string function instead")]
     pub fn load_string(&mut self) -> String {
-        let data: &[u8] = unsafe {
-            let mut len = 0;
-            let ptr = raw::RedisModule_LoadStringBuffer.unwrap()(self.ptr, &mut len);
-            std::slice::from_raw_parts(ptr as *const u8, len)
-        };
-        String::from_utf8(data.to_vec()).unwrap()
+        let buf = self.load_buffer().unwrap();
+        String::from_utf8(buf.to_vec()).unwrap()
+    }
+    /// In the context of the rdb_save method of a module type, saves a
+    /// binary safe buffer into the RDB file.
+    pub fn save_bytes(&mut self, value: &[u8]) {
+        unsafe {
+            raw::RedisModule_SaveStringBuffer.unwrap()(
+                self.ptr,
+                value.as_ptr() as *const c_char,
+                value.len(),
+            )
+        }
+    }
+    /// In the context of the rdb_load method of a module data type, loads a buffer
+    /// from the RDB file, that was previously saved with `IO::save_bytes`.
+    ///
+    /// Buffers longer than `MAX_LOAD_BYTES_LEN` are rejected. The length is only known
+    /// after Redis has loaded the whole buffer, so this bounds the copy into the `Vec`,
+    /// not the allocation done by Redis.
+    pub fn load_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        self.load_bytes_max(MAX_LOAD_BYTES_LEN)
+    }
+    /// Like `IO::load_bytes` but buffers longer than `max_len` are rejected.
+    ///
+    /// The buffer is still loaded whole by Redis before its length is checked, so
+    /// `max_len` doesn't bound the memory used while loading a corrupt RDB file.
+    pub fn load_bytes_max(&mut self, max_len: usize) -> Result<Vec<u8>, Error> {
+        let buf = self.load_buffer()?;
+        if buf.len() > max_len {
+            return Err(Error::new(format!(
+                "buffer of {} bytes is longer than {} bytes",
+                buf.len(),
+                max_len
+            )));
+        }
+        Ok(buf.to_vec())
+    }
+    /// Like `IO::load_bytes` but the buffer allocated by Redis is returned without
+    /// copying it, and is freed when dropped.
+    pub fn load_buffer(&mut self) -> Result<IOBuffer, Error> {
+        let mut len = 0;
+        let ptr = unsafe { raw::RedisModule_LoadStringBuffer.unwrap()(self.ptr, &mut len) };
+        if ptr.is_null() {
+            return Err(Error::new("fail to load buffer"));
+        }
+        Ok(IOBuffer { ptr, len })
     }
     /// In the context of the rdb_save method of a module type, saves a
     /// double into the RDB file.
@@ -86,7 +135,7 @@ impl IO {
     }
     /// In the context of the rdb_load method of a module data type, loads a double
     /// from the RDB file, that was previously saved with `IO::save_double`.
-    pub fn load_double(&self) -> f64 {
+    pub fn load_double(&mut self) -> f64 {
         unsafe { raw::RedisModule_LoadDouble.unwrap()(self.ptr) }
     }
     /// In the context of the rdb_save method of a module type, saves a
@@ -99,30 +148,79 @@ impl IO {
     pub fn load_float(&mut self) -> f32 {
         unsafe { raw::RedisModule_LoadFloat.unwrap()(self.ptr) }
     }
+    /// Like `IO::load_unsigned` but returns `Err` if the load fails.
+    ///
+    /// The `try_load_*` loaders check `IO::have_error` after reading, which
+    /// requires REDISMODULE_OPTIONS_HANDLE_IO_ERRORS to be set with
+    /// `Context::set_module_options`, otherwise Redis aborts on read errors.
+    pub fn try_load_unsigned(&mut self) -> Result<u64, Error> {
+        let value = self.load_unsigned();
+        self.check_io_error("unsigned")?;
+        Ok(value)
+    }
+    /// Like `IO::load_signed` but returns `Err` if the load fails.
+    pub fn try_load_signed(&mut self) -> Result<i64, Error> {
+        let value = self.load_signed();
+        self.check_io_error("signed")?;
+        Ok(value)
+    }
+    /// Like `IO::load_double` but returns `Err` if the load fails.
+    pub fn try_load_double(&mut self) -> Result<f64, Error> {
+        let value = self.load_double();
+        self.check_io_error("double")?;
+        Ok(value)
+    }
+    /// Like `IO::load_float` but returns `Err` if the load fails.
+    pub fn try_load_float(&mut self) -> Result<f32, Error> {
+        let value = self.load_float();
+        self.check_io_error("float")?;
+        Ok(value)
+    }
+    /// Like `IO::load_rstring` but returns `Err` if the load fails.
+    pub fn try_load_rstring(&mut self) -> Result<RString, Error> {
+        let ptr: *mut raw::RedisModuleString =
+            unsafe { raw::RedisModule_LoadString.unwrap()(self.ptr) };
+        if ptr.is_null() {
+            return Err(Error::new("fail to load string"));
+        }
+        Ok(RString::from_ptr(ptr))
+    }
+    /// In the context of the rdb_load method of a module data type, loads a string
+    /// from the RDB file, that was previously saved with `IO::save_string`.
+    ///
+    /// Returns `Err` if the load fails or the string is not utf8.
+    pub fn try_load_string(&mut self) -> Result<String, Error> {
+        let buf = self.load_buffer()?;
+        Ok(String::from_utf8(buf.to_vec())?)
+    }
+    fn check_io_error(&self, kind: &str) -> Result<(), Error> {
+        if self.have_error() {
+            return Err(Error::new(format!("fail to load {}", kind)));
+        }
+        Ok(())
+    }
     /// Emits a command into the AOF during the AOF rewriting process. This function
     /// is only called in the context of the aof_rewrite method of data types exported
     /// by a module. The command works exactly like `Context::Call` in the way
     /// the parameters are passed, but it does not return anything as the error
     /// handling is performed by Redis itself.
-    pub fn emit_aof<T: AsRef<str>>(&mut self, command: T, args: &[T]) {
-        let terminated_args: Vec<CString> = args
-            .iter()
-            .map(|s| CString::new(s.as_ref()).unwrap())
-            .collect();
-
-        let inner_args: Vec<_> = terminated_args.iter().map(|s| s.as_ptr()).collect();
-        let flags: CString = CString::new("v").unwrap();
-
-        let cmd = CString::new(command.as_ref()).unwrap();
-
+    ///
+    /// The arguments are binary safe, see `ToCallArg`. Use `AofBatcher` to emit
+    /// the elements of a collection with variadic commands.
+    pub fn emit_aof<T: AsRef<str>, A: ToCallArg>(&mut self, command: T, args: &[A]) {
+        let call_args: Vec<CallArg> = args.iter().map(|v| v.to_call_arg()).collect();
+        self.emit_aof_args(command.as_ref(), &call_args)
+    }
+    fn emit_aof_args(&mut self, command: &str, args: &[CallArg]) {
+        let args: Vec<*mut raw::RedisModuleString> = args.iter().map(|v| v.get_ptr()).collect();
+        let cmd = CString::new(command).unwrap();
         unsafe {
-            let p_call = raw::RedisModule_EmitAOF.unwrap();
-            p_call(
+            raw::RedisModule_EmitAOF.unwrap()(
                 self.ptr,
                 cmd.as_ptr(),
-                flags.as_ptr(),
-                inner_args.as_ptr() as *mut c_char,
-                terminated_args.len(),
+                b"v\0".as_ptr() as *const c_char,
+                args.as_ptr() as *mut c_char,
+                args.len(),
             )
         };
     }
@@ -133,8 +231,17 @@ impl IO {
     /// critical reason.
     pub fn log_io_error(&self, level: LogLevel, message: &str) {
         let level: CString = level.into();
-        let fmt = CString::new(message).unwrap();
-        unsafe { raw::RedisModule_LogIOError.unwrap()(self.ptr, level.as_ptr(), fmt.as_ptr()) }
+        let message = CString::new(message).unwrap();
+        // The message is not used as the format, it may contain '%'
+        let fmt = CString::new("%s").unwrap();
+        unsafe {
+            raw::RedisModule_LogIOError.unwrap()(
+                self.ptr,
+                level.as_ptr(),
+                fmt.as_ptr(),
+                message.as_ptr(),
+            )
+        }
     }
     pub fn get_ctx(&self) -> Context {
         let ptr: *mut raw::RedisModuleCtx =
@@ -160,6 +267,25 @@ impl IO {
     }
 }
 
+/// A buffer loaded by `IO::load_buffer`, freed on drop
+pub struct IOBuffer {
+    ptr: *mut c_char,
+    len: usize,
+}
+
+impl Deref for IOBuffer {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for IOBuffer {
+    fn drop(&mut self) {
+        unsafe { raw::RedisModule_Free.unwrap()(self.ptr as *mut c_void) }
+    }
+}
+
 /// Wrap the pointer of a RedisModuleDigest
 #[repr(C)]
 pub struct Digest {
@@ -219,12 +345,16 @@ impl Digest {
     ///     EndSequence();
     ///
     pub fn add_string<T: AsRef<str>>(&mut self, s: T) {
-        let s_ = CString::new(s.as_ref()).unwrap();
+        self.add_bytes(s.as_ref())
+    }
+    /// Like `Digest::add_string`, but the element is binary safe
+    pub fn add_bytes<T: AsRef<[u8]>>(&mut self, s: T) {
+        let s = s.as_ref();
         unsafe {
             raw::RedisModule_DigestAddStringBuffer.unwrap()(
                 self.ptr,
-                s_.as_ptr() as *mut c_uchar,
-                s.as_ref().len(),
+                s.as_ptr() as *mut c_uchar,
+                s.len(),
             )
         }
     }
@@ -233,8 +363,422 @@ impl Digest {
     pub fn add_integer(&mut self, i: i64) {
         unsafe { raw::RedisModule_DigestAddLongLong.unwrap()(self.ptr, i) }
     }
-    /// See `Context:add_string_buffer`
-    pub fn end_sequeue(&mut self) {
+    /// End the sequence of the elements added, see `Digest::add_string`
+    pub fn end_sequence(&mut self) {
         unsafe { raw::RedisModule_DigestEndSequence.unwrap()(self.ptr) }
     }
+    #[deprecated(note = "Please use the end_sequence function instead")]
+    pub fn end_sequeue(&mut self) {
+        self.end_sequence()
+    }
+}
+
+/// The number of elements of a command emitted by `AofBatcher`, it is the one
+/// Redis uses to rewrite its own types.
+pub const AOF_REWRITE_ITEMS_PER_CMD: usize = 64;
+
+/// Emit the elements of a collection into the AOF with variadic commands,
+/// like `HELLOTYPE.INSERT key a b c ...`.
+///
+/// The command is emitted when it has `max_items` elements, the remaining elements
+/// are emitted by `AofBatcher::finish` or when the batcher is dropped.
+/// ```rust,no_run
+/// fn aof_rewrite<T: AsRef<str>>(&self, io: &mut IO, key: T) {
+///     let mut batcher = AofBatcher::new(io, "HELLOTYPE.INSERT", &[key.as_ref()]);
+///     for v in self.iter() {
+///         batcher.push(v);
+///     }
+/// }
+/// ```
+pub struct AofBatcher<'a> {
+    io: &'a mut IO,
+    command: String,
+    args: Vec<CallArg<'static>>,
+    prefix_len: usize,
+    items: usize,
+    max_items: usize,
+}
+
+impl<'a> AofBatcher<'a> {
+    /// Create a batcher emitting `command`, each command starts with the `prefix`
+    /// arguments, like the key.
+    pub fn new<T: AsRef<str>, A: ToCallArg>(io: &'a mut IO, command: T, prefix: &[A]) -> Self {
+        let args: Vec<CallArg<'static>> = prefix
+            .iter()
+            .map(|v| CallArg::Owned(v.to_call_arg().into_owned()))
+            .collect();
+        AofBatcher {
+            io,
+            command: command.as_ref().to_owned(),
+            prefix_len: args.len(),
+            args,
+            items: 0,
+            max_items: AOF_REWRITE_ITEMS_PER_CMD,
+        }
+    }
+    /// Set the maximum number of elements of a command, it is at least 1
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items.max(1);
+        self
+    }
+    /// Add an element of one argument
+    pub fn push<A: ToCallArg + ?Sized>(&mut self, arg: &A) {
+        self.args
+            .push(CallArg::Owned(arg.to_call_arg().into_owned()));
+        self.end_item();
+    }
+    /// Add an element of several arguments, like the score and the member of a
+    /// sorted set, which are always emitted in the same command.
+    pub fn push_group<A: ToCallArg>(&mut self, args: &[A]) {
+        self.args.extend(
+            args.iter()
+                .map(|v| CallArg::Owned(v.to_call_arg().into_owned())),
+        );
+        self.end_item();
+    }
+    /// Emit the command of the remaining elements, if any
+    pub fn flush(&mut self) {
+        if self.items == 0 {
+            return;
+        }
+        self.io.emit_aof_args(&self.command, &self.args);
+        self.args.truncate(self.prefix_len);
+        self.items = 0;
+    }
+    /// Emit the remaining elements, it is the same as dropping the batcher
+    pub fn finish(mut self) {
+        self.flush()
+    }
+    fn end_item(&mut self) {
+        self.items += 1;
+        if self.items >= self.max_items {
+            self.flush();
+        }
+    }
+}
+
+impl Drop for AofBatcher<'_> {
+    fn drop(&mut self) {
+        self.flush()
+    }
+}
+
+/// A value which can be added to a `Digest`, for `DEBUG DIGEST` and `DEBUG DIGEST-VALUE`.
+///
+/// The elements of ordered values, like `Vec` or `BTreeMap`, are added in order to
+/// the current sequence. The elements of unordered values, like `HashMap` or `HashSet`,
+/// are each added as a sequence, so the digest does not depend on the iteration order;
+/// the current sequence is ended before. Collections are preceded by their length
+/// and `Option` by 0 for `None` or 1 for `Some`, so nested values are not ambiguous.
+///
+/// Integers are added as their little endian bytes, `usize` and `isize` as 64 bits
+/// ones, so the digest is the same on every platform.
+///
+/// It can be derived, the fields are added in order and the fields marked with
+/// `#[digest(skip)]` are ignored. Use the `digest` attr of `rtypedef` to digest a
+/// data type with it.
+/// ```rust,no_run
+/// #[derive(Digestable)]
+/// pub struct MyType {
+///     pub items: Vec<String>,
+///     pub tags: HashSet<String>,
+///     #[digest(skip)]
+///     pub cache: Option<usize>,
+/// }
+/// ```
+pub trait Digestable {
+    /// Add the value to the digest
+    fn digest(&self, digest: &mut Digest);
+}
+
+macro_rules! impl_digestable_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Digestable for $t {
+                fn digest(&self, digest: &mut Digest) {
+                    digest.add_bytes(self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_digestable_integer!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+impl Digestable for isize {
+    fn digest(&self, digest: &mut Digest) {
+        (*self as i64).digest(digest)
+    }
+}
+
+impl Digestable for usize {
+    fn digest(&self, digest: &mut Digest) {
+        (*self as u64).digest(digest)
+    }
+}
+
+impl Digestable for bool {
+    fn digest(&self, digest: &mut Digest) {
+        (*self as u8).digest(digest)
+    }
+}
+
+macro_rules! impl_digestable_display {
+    ($($t:ty),*) => {
+        $(
+            impl Digestable for $t {
+                fn digest(&self, digest: &mut Digest) {
+                    digest.add_string(self.to_string());
+                }
+            }
+        )*
+    };
+}
+
+impl_digestable_display!(f32, f64, char);
+
+impl Digestable for str {
+    fn digest(&self, digest: &mut Digest) {
+        digest.add_bytes(self)
+    }
+}
+
+impl Digestable for String {
+    fn digest(&self, digest: &mut Digest) {
+        digest.add_bytes(self)
+    }
+}
+
+impl Digestable for RStr {
+    fn digest(&self, digest: &mut Digest) {
+        digest.add_bytes(self.get_buffer())
+    }
+}
+
+impl Digestable for RString {
+    fn digest(&self, digest: &mut Digest) {
+        digest.add_bytes(self.get_buffer())
+    }
+}
+
+impl<T: Digestable + ?Sized> Digestable for &T {
+    fn digest(&self, digest: &mut Digest) {
+        (**self).digest(digest)
+    }
+}
+
+impl<T: Digestable + ?Sized> Digestable for Box<T> {
+    fn digest(&self, digest: &mut Digest) {
+        (**self).digest(digest)
+    }
+}
+
+impl<T: Digestable> Digestable for Option<T> {
+    fn digest(&self, digest: &mut Digest) {
+        match self {
+            Some(v) => {
+                digest.add_integer(1);
+                v.digest(digest)
+            }
+            None => digest.add_integer(0),
+        }
+    }
+}
+
+impl<T: Digestable> Digestable for [T] {
+    fn digest(&self, digest: &mut Digest) {
+        digest.add_integer(self.len() as i64);
+        self.iter().for_each(|v| v.digest(digest))
+    }
+}
+
+impl<T: Digestable> Digestable for Vec<T> {
+    fn digest(&self, digest: &mut Digest) {
+        self.as_slice().digest(digest)
+    }
+}
+
+impl<T: Digestable> Digestable for VecDeque<T> {
+    fn digest(&self, digest: &mut Digest) {
+        digest.add_integer(self.len() as i64);
+        self.iter().for_each(|v| v.digest(digest))
+    }
+}
+
+impl<T: Digestable> Digestable for BTreeSet<T> {
+    fn digest(&self, digest: &mut Digest) {
+        digest.add_integer(self.len() as i64);
+        self.iter().for_each(|v| v.digest(digest))
+    }
+}
+
+impl<K: Digestable, V: Digestable> Digestable for BTreeMap<K, V> {
+    fn digest(&self, digest: &mut Digest) {
+        digest.add_integer(self.len() as i64);
+        self.iter().for_each(|(k, v)| {
+            k.digest(digest);
+            v.digest(digest);
+        })
+    }
+}
+
+impl<T: Digestable, S> Digestable for HashSet<T, S> {
+    fn digest(&self, digest: &mut Digest) {
+        digest.add_integer(self.len() as i64);
+        digest.end_sequence();
+        self.iter().for_each(|v| {
+            v.digest(digest);
+            digest.end_sequence();
+        })
+    }
+}
+
+impl<K: Digestable, V: Digestable, S> Digestable for HashMap<K, V, S> {
+    fn digest(&self, digest: &mut Digest) {
+        digest.add_integer(self.len() as i64);
+        digest.end_sequence();
+        self.iter().for_each(|(k, v)| {
+            k.digest(digest);
+            v.digest(digest);
+            digest.end_sequence();
+        })
+    }
+}
+
+macro_rules! impl_digestable_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Digestable),+> Digestable for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn digest(&self, digest: &mut Digest) {
+                let ($($name,)+) = self;
+                $($name.digest(digest);)+
+            }
+        }
+    };
+}
+
+impl_digestable_tuple!(A);
+impl_digestable_tuple!(A, B);
+impl_digestable_tuple!(A, B, C);
+impl_digestable_tuple!(A, B, C, D);
+impl_digestable_tuple!(A, B, C, D, E);
+impl_digestable_tuple!(A, B, C, D, E, F);
+
+/// Wrap the pointer of a RedisModuleKeyOptCtx, which tells the context-aware
+/// type methods like `TypeMethod::copy2` which keys they are called for.
+pub struct KeyOptCtx {
+    ptr: *mut raw::RedisModuleKeyOptCtx,
+}
+
+impl GetPtr for KeyOptCtx {
+    type PtrType = raw::RedisModuleKeyOptCtx;
+    fn get_ptr(&self) -> *mut Self::PtrType {
+        self.ptr
+    }
+}
+
+impl FromPtr for KeyOptCtx {
+    type PtrType = raw::RedisModuleKeyOptCtx;
+    fn from_ptr(ptr: *mut raw::RedisModuleKeyOptCtx) -> KeyOptCtx {
+        KeyOptCtx { ptr }
+    }
+}
+
+impl KeyOptCtx {
+    /// Return the name of the key the value belongs to
+    pub fn get_keyname(&self) -> RStr {
+        let ptr = unsafe { raw::RedisModule_GetKeyNameFromOptCtx.unwrap()(self.ptr) };
+        RStr::from_ptr(ptr as *mut raw::RedisModuleString)
+    }
+    /// Return the name of the destination key, only set in `TypeMethod::copy2`
+    pub fn get_to_keyname(&self) -> Option<RStr> {
+        let ptr = unsafe { raw::RedisModule_GetToKeyNameFromOptCtx.unwrap()(self.ptr) };
+        if ptr.is_null() {
+            return None;
+        }
+        Some(RStr::from_ptr(ptr as *mut raw::RedisModuleString))
+    }
+    /// Return the id of the db the value belongs to
+    pub fn get_db(&self) -> i32 {
+        unsafe { raw::RedisModule_GetDbIdFromOptCtx.unwrap()(self.ptr) }
+    }
+    /// Return the id of the destination db, only set in `TypeMethod::copy2`
+    pub fn get_to_db(&self) -> Option<i32> {
+        let db = unsafe { raw::RedisModule_GetToDbIdFromOptCtx.unwrap()(self.ptr) };
+        if db < 0 {
+            return None;
+        }
+        Some(db)
+    }
+}
+
+/// Wrap the pointer of a RedisModuleDefragCtx, passed to `TypeMethod::defrag`
+pub struct DefragCtx {
+    ptr: *mut raw::RedisModuleDefragCtx,
+}
+
+impl GetPtr for DefragCtx {
+    type PtrType = raw::RedisModuleDefragCtx;
+    fn get_ptr(&self) -> *mut Self::PtrType {
+        self.ptr
+    }
+}
+
+impl FromPtr for DefragCtx {
+    type PtrType = raw::RedisModuleDefragCtx;
+    fn from_ptr(ptr: *mut raw::RedisModuleDefragCtx) -> DefragCtx {
+        DefragCtx { ptr }
+    }
+}
+
+impl DefragCtx {
+    /// Move an allocation to a less fragmented place, the same allocation is
+    /// returned when it does not need to be moved.
+    pub fn alloc<T>(&mut self, value: Box<T>) -> Box<T> {
+        if std::mem::size_of::<T>() == 0 {
+            return value;
+        }
+        let ptr = Box::into_raw(value);
+        unsafe {
+            let new_ptr = raw::RedisModule_DefragAlloc.unwrap()(self.ptr, ptr as *mut c_void);
+            if new_ptr.is_null() {
+                Box::from_raw(ptr)
+            } else {
+                Box::from_raw(new_ptr as *mut T)
+            }
+        }
+    }
+    /// Like `DefragCtx::alloc`, for a redis string which is not shared
+    pub fn alloc_string(&mut self, value: RString) -> RString {
+        let new_ptr =
+            unsafe { raw::RedisModule_DefragRedisModuleString.unwrap()(self.ptr, value.get_ptr()) };
+        if new_ptr.is_null() {
+            return value;
+        }
+        std::mem::forget(value);
+        RString::from_ptr(new_ptr)
+    }
+    /// Return true when the time budget of the defrag is exhausted, the defrag
+    /// should then save its position with `DefragCtx::set_cursor` and return false.
+    pub fn should_stop(&self) -> bool {
+        unsafe { raw::RedisModule_DefragShouldStop.unwrap()(self.ptr) != 0 }
+    }
+    /// Save the position to resume the defrag from, when `TypeMethod::defrag`
+    /// is called again for the same key.
+    pub fn set_cursor(&mut self, cursor: u64) -> Result<(), Error> {
+        handle_status(
+            unsafe { raw::RedisModule_DefragCursorSet.unwrap()(self.ptr, cursor as c_ulong) },
+            "fail to set defrag cursor",
+        )
+    }
+    /// Return the position saved by the previous call, `None` when the defrag of
+    /// the key starts.
+    pub fn get_cursor(&self) -> Option<u64> {
+        let mut cursor: c_ulong = 0;
+        let status = unsafe { raw::RedisModule_DefragCursorGet.unwrap()(self.ptr, &mut cursor) };
+        if status != raw::REDISMODULE_OK as i32 {
+            return None;
+        }
+        Some(cursor as u64)
+    }
 }
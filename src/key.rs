@@ -1,8 +1,11 @@
 //! A implementation of Redis key
+use std::ffi::CString;
+use std::marker::PhantomData;
 use std::ops::Deref;
 use std::os::raw::{c_int, c_void};
 use std::time::Duration;
 
+use crate::context::{self, Context};
 use crate::error::Error;
 use crate::raw;
 use crate::rtype::RType;
@@ -13,32 +16,45 @@ use crate::{handle_status, FromPtr, GetPtr};
 /// Repersent a Redis key with read permision
 ///
 /// create with [`ctx.open_read_key`](./context/struct.Context.html#method.open_read_key)
-pub struct ReadKey {
+///
+/// The key borrows the context which opened it, so it can not outlive the command,
+/// and the methods of `Context` which may change the keyspace, like `Context::call`
+/// or `Context::open_write_key`, can't be used while the key is open.
+pub struct ReadKey<'a> {
     ptr: *mut raw::RedisModuleKey,
     ctx: *mut raw::RedisModuleCtx,
+    _marker: PhantomData<&'a Context>,
 }
 
-impl GetPtr for ReadKey {
+impl<'a> GetPtr for ReadKey<'a> {
     type PtrType = raw::RedisModuleKey;
     fn get_ptr(&self) -> *mut Self::PtrType {
         self.ptr
     }
 }
 
-impl Drop for ReadKey {
+impl<'a> Drop for ReadKey<'a> {
     fn drop(&mut self) {
         unsafe { raw::RedisModule_CloseKey.unwrap()(self.ptr) };
     }
 }
 
-impl ReadKey {
-    pub fn new(ctx: *mut raw::RedisModuleCtx, keyname: &RStr) -> Self {
-        let mode = raw::REDISMODULE_READ as c_int;
+impl<'a> ReadKey<'a> {
+    pub fn new(ctx: &'a Context, keyname: &RStr) -> Self {
+        ReadKey::open(ctx, keyname, raw::REDISMODULE_READ as c_int)
+    }
+
+    pub(crate) fn open(ctx: &'a Context, keyname: &RStr, mode: c_int) -> Self {
+        let ctx = ctx.get_ptr();
         let ptr = unsafe {
             raw::RedisModule_OpenKey.unwrap()(ctx, keyname.get_ptr(), mode)
                 as *mut raw::RedisModuleKey
         };
-        ReadKey { ptr, ctx }
+        ReadKey {
+            ptr,
+            ctx,
+            _marker: PhantomData,
+        }
     }
 
     /// Where the key pointer is NULL
@@ -50,14 +66,13 @@ impl ReadKey {
     /// Assuming `get_type` returned REDISMODULE_KEYTYPE_MODULE on
     /// the key, returns the module type low-level value stored at key, as
     /// it was set by the user via `set_value`.
-    pub fn get_value<T>(&self, redis_type: &RType<T>) -> Result<Option<&mut T>, Error> {
+    pub fn get_value<T>(&self, redis_type: &RType<T>) -> Result<Option<&T>, Error> {
         let exist = self.check_module_type(redis_type)?;
         if !exist {
             return Ok(None);
         }
-        let value = unsafe { raw::RedisModule_ModuleTypeGetValue.unwrap()(self.ptr) as *mut T };
-        let value = unsafe { &mut *value };
-        Ok(Some(value))
+        let value = unsafe { raw::RedisModule_ModuleTypeGetValue.unwrap()(self.ptr) as *const T };
+        Ok(Some(unsafe { &*value }))
     }
 
     /// Check the key type.
@@ -315,36 +330,117 @@ impl ReadKey {
 /// Repersent a Redis key with read and write permision
 ///
 /// create with [`ctx.open_write_key`](./context/struct.Context.html#method.open_write_key)
-pub struct WriteKey {
-    read_key: ReadKey,
+///
+/// Methods which may change or free the value of the key take `&mut self`,
+/// so no reference obtained from the key survives them. The key borrows the context
+/// mutably, so no other key can be opened and no command can be called with the
+/// context while the key is open.
+///
+/// The key remembers whether it was modified. When it is committed or dropped,
+/// the event configured with `WriteKey::set_notify_event` is fired.
+///
+/// By default Redis signals every key opened for writing when it is closed. If the
+/// module set REDISMODULE_OPTION_NO_IMPLICIT_SIGNAL_MODIFIED, Redis doesn't, and the
+/// key signals the modification itself when it is committed or dropped, only if it
+/// was modified.
+pub struct WriteKey<'a> {
+    read_key: ReadKey<'a>,
+    db: i32,
+    modified: bool,
+    notify_event: Option<(i32, CString)>,
+}
+
+impl<'a> Drop for WriteKey<'a> {
+    fn drop(&mut self) {
+        let _ = self.commit();
+    }
 }
 
-impl AsRef<ReadKey> for WriteKey {
-    fn as_ref(&self) -> &ReadKey {
+impl<'a> AsRef<ReadKey<'a>> for WriteKey<'a> {
+    fn as_ref(&self) -> &ReadKey<'a> {
         &self.read_key
     }
 }
 
-impl Deref for WriteKey {
-    type Target = ReadKey;
+impl<'a> Deref for WriteKey<'a> {
+    type Target = ReadKey<'a>;
     fn deref(&self) -> &Self::Target {
         self.as_ref()
     }
 }
 
-impl WriteKey {
-    pub fn new(ctx: *mut raw::RedisModuleCtx, keyname: &RStr) -> Self {
+impl<'a> WriteKey<'a> {
+    pub fn new(ctx: &'a mut Context, keyname: &RStr) -> Self {
+        WriteKey::open(ctx, keyname)
+    }
+    /// Open the key without borrowing the context mutably, the caller must make sure
+    /// no other handle of the key is open while the value is borrowed.
+    pub(crate) fn open(ctx: &'a Context, keyname: &RStr) -> Self {
         let mode = (raw::REDISMODULE_READ | raw::REDISMODULE_WRITE) as c_int;
-        let ptr = unsafe {
-            raw::RedisModule_OpenKey.unwrap()(ctx, keyname.get_ptr(), mode)
-                as *mut raw::RedisModuleKey
-        };
         WriteKey {
-            read_key: ReadKey { ptr, ctx },
+            read_key: ReadKey::open(ctx, keyname, mode),
+            db: ctx.get_select_db() as i32,
+            modified: false,
+            notify_event: None,
         }
     }
+    /// Whether the key was modified since it was opened or last committed.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+    /// Keyspace event fired when the modified key is committed or dropped,
+    /// `type_` is one of REDISMODULE_NOTIFY_*, see `Context::notify_keyspace_event`.
+    pub fn set_notify_event<T: AsRef<str>>(&mut self, type_: i32, event: T) {
+        let event = CString::new(event.as_ref()).unwrap();
+        self.notify_event = Some((type_, event));
+    }
+    /// Signal the modification of the key, when Redis doesn't signal it implicitly,
+    /// and fire the configured keyspace event, if the key was modified. Dropping the
+    /// key commits it as well.
+    pub fn commit(&mut self) -> Result<(), Error> {
+        if !self.modified {
+            return Ok(());
+        }
+        self.modified = false;
+        let keyname = self.get_keyname();
+        // Both APIs use the selected DB, which may differ from the DB of the key
+        let mut ctx = Context::from_ptr(self.ctx);
+        let _guard = ctx.select_db_scoped(self.db)?;
+        // Otherwise Redis signals the key when it is closed
+        if !context::is_implicit_signal_modified() {
+            handle_status(
+                unsafe { raw::RedisModule_SignalModifiedKey.unwrap()(self.ctx, keyname.get_ptr()) },
+                "fail to signal key modified",
+            )?;
+        }
+        if let Some((type_, event)) = &self.notify_event {
+            handle_status(
+                unsafe {
+                    raw::RedisModule_NotifyKeyspaceEvent.unwrap()(
+                        self.ctx,
+                        *type_,
+                        event.as_ptr(),
+                        keyname.get_ptr(),
+                    )
+                },
+                "fail to notify keyspace event",
+            )?;
+        }
+        Ok(())
+    }
+    /// Like `ReadKey::get_value`, but the value is borrowed mutably from the key,
+    /// so the key is marked as modified. Use `ReadKey::get_value` to only read it.
+    pub fn get_value_mut<T>(&mut self, redis_type: &RType<T>) -> Result<Option<&mut T>, Error> {
+        let exist = self.check_module_type(redis_type)?;
+        if !exist {
+            return Ok(None);
+        }
+        let value = unsafe { raw::RedisModule_ModuleTypeGetValue.unwrap()(self.ptr) as *mut T };
+        self.modified = true;
+        Ok(Some(unsafe { &mut *value }))
+    }
     /// Set the specified module type object as the value of the key, deleting the old value if any.
-    pub fn set_value<T>(&self, redis_type: &RType<T>, value: T) -> Result<&mut T, Error> {
+    pub fn set_value<T>(&mut self, redis_type: &RType<T>, value: T) -> Result<&mut T, Error> {
         let value = Box::into_raw(Box::new(value)) as *mut c_void;
         handle_status(
             unsafe {
@@ -356,6 +452,7 @@ impl WriteKey {
             },
             "fail to set value",
         )?;
+        self.modified = true;
         Ok(unsafe { &mut *(value as *mut T) })
     }
     ///  Replace the value assigned to a module type.
@@ -375,7 +472,7 @@ impl WriteKey {
     ///
     ///  If old_value is non-NULL, the old value is returned by reference.
     pub fn replace_value<T>(
-        &self,
+        &mut self,
         redis_type: &RType<T>,
         value: T,
     ) -> Result<(&mut T, Box<T>), Error> {
@@ -392,65 +489,117 @@ impl WriteKey {
             },
             "fail to replace value",
         )?;
+        self.modified = true;
         Ok(unsafe { (&mut *(value as *mut T), Box::from_raw(old_value as *mut T)) })
     }
+    /// Gets the module type value of the key for in-place manipulation.
+    ///
+    /// The entry is `Entry::WrongType` when the key holds a value of a
+    /// different type, so commands only have to handle that case once.
+    ///
+    /// ```rust,no_run
+    /// let mut key = ctx.open_write_key(&args[1]);
+    /// let hto = key.entry(&HELLOTYPE).or_insert_with(HelloTypeNode::new)?;
+    /// hto.push(value);
+    /// ```
+    pub fn entry<'b, T>(&'b mut self, redis_type: &'b RType<T>) -> Entry<'b, 'a, T> {
+        match self.check_module_type(redis_type) {
+            Ok(true) => Entry::Occupied(OccupiedEntry {
+                key: self,
+                redis_type,
+            }),
+            Ok(false) => Entry::Vacant(VacantEntry {
+                key: self,
+                redis_type,
+            }),
+            Err(_) => Entry::WrongType,
+        }
+    }
     /// Remove the key, and setup the key to accept new writes as an empty
     /// key (that will be created on demand).
-    pub fn delete(&self) -> Result<(), Error> {
+    pub fn delete(&mut self) -> Result<(), Error> {
         handle_status(
             unsafe { raw::RedisModule_DeleteKey.unwrap()(self.ptr) },
             "fail to execute delete",
-        )
+        )?;
+        self.modified = true;
+        Ok(())
     }
     /// Unlink the key (that is delete it in a non-blocking way, not reclaiming
     /// memory immediately) and setup the key to  accept new writes as
     /// an empty key (that will be created on demand).
-    pub fn unlink(&self) -> Result<(), Error> {
+    pub fn unlink(&mut self) -> Result<(), Error> {
         handle_status(
             unsafe { raw::RedisModule_UnlinkKey.unwrap()(self.ptr) },
             "fail to execute unlink",
-        )
+        )?;
+        self.modified = true;
+        Ok(())
     }
     /// Set new expire for the key.
     /// If the special expire REDISMODULE_NO_EXPIRE is set, the expire is
     /// cancelled if there was one (the same as the PERSIST command).
     /// Note that the expire must be provided as a positive integer representing
     /// the number of milliseconds of TTL the key should have.
-    pub fn set_expire(&self, expire_ms: Duration) -> Result<(), Error> {
+    pub fn set_expire(&mut self, expire_ms: Duration) -> Result<(), Error> {
         handle_status(
             unsafe { raw::RedisModule_SetExpire.unwrap()(self.ptr, expire_ms.as_millis() as i64) },
             "fail to execute set_expire",
-        )
+        )?;
+        self.modified = true;
+        Ok(())
     }
     /// Set the specified string 'str' as the value of the key, deleting the old value if any.
-    pub fn string_set(&self, value: &RStr) -> Result<(), Error> {
+    pub fn string_set(&mut self, value: &RStr) -> Result<(), Error> {
         handle_status(
             unsafe { raw::RedisModule_StringSet.unwrap()(self.ptr, value.get_ptr()) },
             "fail to execute string_set",
-        )
+        )?;
+        self.modified = true;
+        Ok(())
+    }
+    /// Prepare the string value of the key for direct memory access, the bytes
+    /// can be modified in place but the length of the string stays the same.
+    pub fn string_dma(&mut self) -> Result<&mut [u8], Error> {
+        let mut len = 0;
+        let data = unsafe {
+            raw::RedisModule_StringDMA.unwrap()(
+                self.ptr,
+                &mut len,
+                (raw::REDISMODULE_READ | raw::REDISMODULE_WRITE) as c_int,
+            ) as *mut u8
+        };
+        if data.is_null() {
+            return Err(Error::new("fail to get string value"));
+        }
+        self.modified = true;
+        Ok(unsafe { std::slice::from_raw_parts_mut(data, len) })
     }
     /// Push an element into a list
-    pub fn list_push(&self, position: ListPosition, value: &RStr) -> Result<(), Error> {
+    pub fn list_push(&mut self, position: ListPosition, value: &RStr) -> Result<(), Error> {
         handle_status(
             unsafe {
                 raw::RedisModule_ListPush.unwrap()(self.ptr, position as i32, value.get_ptr())
             },
             "fail to execute list_push",
-        )
+        )?;
+        self.modified = true;
+        Ok(())
     }
     /// Pop an element from the list, and returns it.
-    pub fn list_pop(&self, pos: ListPosition) -> Result<RString, Error> {
+    pub fn list_pop(&mut self, pos: ListPosition) -> Result<RString, Error> {
         let p = unsafe { raw::RedisModule_ListPop.unwrap()(self.ptr, pos as i32) };
         if p.is_null() {
             return Err(Error::new("fail to pop list"));
         }
+        self.modified = true;
         Ok(RString::from_ptr(p))
     }
     /// Set the field of the specified hash field to the specified value.
     ///
     /// If value is none, it will clear the field.
     pub fn hash_set(
-        &self,
+        &mut self,
         flag: Option<HashSetFlag>,
         field: &RStr,
         value: Option<&RStr>,
@@ -469,12 +618,13 @@ impl WriteKey {
                 "fail to execute hash_set",
             )?;
         }
+        self.modified = true;
         Ok(())
     }
     /// Add a new element into a sorted set, with the specified 'score'.
     /// If the element already exists, the score is updated.
     pub fn zset_add(
-        &self,
+        &mut self,
         score: f64,
         ele: &RStr,
         flag: Option<ZaddInputFlag>,
@@ -491,6 +641,7 @@ impl WriteKey {
             )?;
             out_flag = flag_.into();
         }
+        self.modified = true;
         Ok(out_flag)
     }
     /// This function works exactly like `WriteKey::zset_add`, but instead of setting
@@ -498,7 +649,7 @@ impl WriteKey {
     /// element does not already exist, it is added assuming the old score was
     /// zero.
     pub fn zset_incrby(
-        &self,
+        &mut self,
         ele: &RStr,
         score: f64,
         flag: Option<ZaddInputFlag>,
@@ -522,12 +673,13 @@ impl WriteKey {
             )?;
             out_flag = flag_.into();
         }
+        self.modified = true;
         Ok((out_flag, new_score))
     }
     /// Remove the specified element from the sorted set.
     ///
     /// The bool indicate Whether the element was removed
-    pub fn zset_rem(&self, ele: &RStr) -> Result<bool, Error> {
+    pub fn zset_rem(&mut self, ele: &RStr) -> Result<bool, Error> {
         let mut flag = 0;
         unsafe {
             handle_status(
@@ -536,6 +688,7 @@ impl WriteKey {
             )?;
         }
         let result = if flag == 0 { false } else { true };
+        self.modified |= result;
         Ok(result)
     }
     /// On success retrieve the double score associated at the sorted set element 'ele'.
@@ -551,7 +704,7 @@ impl WriteKey {
     }
     /// Set the key access frequency. only relevant if the server's maxmemory policy
     /// is LFU based.
-    pub fn set_lfu(&self, freq: u64) -> Result<(), Error> {
+    pub fn set_lfu(&mut self, freq: u64) -> Result<(), Error> {
         handle_status(
             unsafe { raw::RedisModule_SetLFU.unwrap()(self.ptr, freq as i64) },
             "fail to set lfu",
@@ -559,7 +712,7 @@ impl WriteKey {
     }
     /// Set the key last access time for LRU based eviction. not relevent if the
     /// servers's maxmemory policy is LFU based. Value is idle time in milliseconds.
-    pub fn set_lru(&self, time_ms: Duration) -> Result<(), Error> {
+    pub fn set_lru(&mut self, time_ms: Duration) -> Result<(), Error> {
         handle_status(
             unsafe { raw::RedisModule_SetLRU.unwrap()(self.ptr, time_ms.as_millis() as i64) },
             "fail to set lru",
@@ -567,6 +720,122 @@ impl WriteKey {
     }
 }
 
+/// A view into the module type value of a key, created with `WriteKey::entry`
+pub enum Entry<'a, 'k, T> {
+    /// The key holds a value of the requested module type
+    Occupied(OccupiedEntry<'a, 'k, T>),
+    /// The key is empty
+    Vacant(VacantEntry<'a, 'k, T>),
+    /// The key holds a value of another type
+    WrongType,
+}
+
+impl<'a, 'k, T> Entry<'a, 'k, T> {
+    /// Ensures a value is in the entry by inserting the default if empty,
+    /// and returns a mutable reference to the value.
+    pub fn or_insert(self, default: T) -> Result<&'a mut T, Error> {
+        self.or_insert_with(|| default)
+    }
+    /// Ensures a value is in the entry by inserting the result of the default
+    /// function if empty, and returns a mutable reference to the value.
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> Result<&'a mut T, Error> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(default()),
+            Entry::WrongType => Err(Error::WrongType),
+        }
+    }
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts.
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            entry => entry,
+        }
+    }
+    /// Returns the occupied entry, None if the key is empty.
+    ///
+    /// Error::WrongType is returned if the key holds a value of another type.
+    pub fn occupied(self) -> Result<Option<OccupiedEntry<'a, 'k, T>>, Error> {
+        match self {
+            Entry::Occupied(entry) => Ok(Some(entry)),
+            Entry::Vacant(_) => Ok(None),
+            Entry::WrongType => Err(Error::WrongType),
+        }
+    }
+}
+
+impl<'a, 'k, T: Default> Entry<'a, 'k, T> {
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value.
+    pub fn or_default(self) -> Result<&'a mut T, Error> {
+        self.or_insert_with(T::default)
+    }
+}
+
+/// A view into a key which holds a value of the module type
+pub struct OccupiedEntry<'a, 'k, T> {
+    key: &'a mut WriteKey<'k>,
+    redis_type: &'a RType<T>,
+}
+
+impl<'a, 'k, T> OccupiedEntry<'a, 'k, T> {
+    fn value_ptr(&self) -> *mut T {
+        unsafe { raw::RedisModule_ModuleTypeGetValue.unwrap()(self.key.ptr) as *mut T }
+    }
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &T {
+        unsafe { &*self.value_ptr() }
+    }
+    /// Gets a mutable reference to the value in the entry.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.key.modified = true;
+        unsafe { &mut *self.value_ptr() }
+    }
+    /// Converts the entry into a mutable reference to the value bound to the
+    /// lifetime of the key.
+    pub fn into_mut(self) -> &'a mut T {
+        self.key.modified = true;
+        unsafe { &mut *self.value_ptr() }
+    }
+    /// Sets the value of the entry, and returns the old value.
+    pub fn insert(&mut self, value: T) -> Result<Box<T>, Error> {
+        let (_, old_value) = self.key.replace_value(self.redis_type, value)?;
+        Ok(old_value)
+    }
+    /// Deletes the key, the value is released with the free method of the type.
+    pub fn remove(self) -> Result<(), Error> {
+        self.key.delete()
+    }
+    /// Deletes the key if `f` returns true for the value, like when a collection
+    /// becomes empty after removing its last element.
+    ///
+    /// The bool indicate whether the key was deleted.
+    pub fn remove_if<F: FnOnce(&T) -> bool>(self, f: F) -> Result<bool, Error> {
+        if !f(self.get()) {
+            return Ok(false);
+        }
+        self.remove()?;
+        Ok(true)
+    }
+}
+
+/// A view into an empty key
+pub struct VacantEntry<'a, 'k, T> {
+    key: &'a mut WriteKey<'k>,
+    redis_type: &'a RType<T>,
+}
+
+impl<'a, 'k, T> VacantEntry<'a, 'k, T> {
+    /// Sets the value of the entry, and returns a mutable reference to it.
+    pub fn insert(self, value: T) -> Result<&'a mut T, Error> {
+        self.key.set_value(self.redis_type, value)
+    }
+}
+
 /// The position of WriteKey::ListPop / WriteKey::ListPush operation
 pub enum ListPosition {
     Head = raw::REDISMODULE_LIST_HEAD as isize,
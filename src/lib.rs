@@ -28,8 +28,10 @@ pub mod raw;
 
 mod alloc;
 pub mod block_client;
+pub mod call_arg;
 pub mod call_reply;
 pub mod cluster;
+pub mod cmd;
 mod common;
 pub mod context;
 pub mod error;
@@ -37,6 +39,9 @@ pub mod io;
 pub mod key;
 mod macros;
 pub mod prelude;
+#[cfg(feature = "serde")]
+pub mod rdb;
+pub mod resp;
 pub mod rtype;
 pub mod scan_cursor;
 pub mod string;
@@ -69,3 +69,16 @@ macro_rules! define_module {
 
     }
 }
+
+/// Call a Redis command with arguments of any `ToCallArg` type
+///
+/// ```rust,no_run
+/// let reply = call!(ctx, "SET", &args[1], b"\x00\x01", "EX", 10)?;
+/// ```
+#[macro_export]
+macro_rules! call {
+    ($ctx:expr, $command:expr $(, $arg:expr)* $(,)*) => {{
+        let args: &[&dyn $crate::call_arg::ToCallArg] = &[$(&$arg),*];
+        $ctx.call($command, None, args)
+    }};
+}
@@ -1,5 +1,9 @@
 //! The RedisModule Prelude.
 
+pub use crate::call;
+pub use crate::call_arg::ToCallArg;
+pub use crate::call_reply::FromCallReply;
+pub use crate::cmd::Cmd;
 pub use crate::context::Context;
 pub use crate::define_module;
 pub use crate::error::Error;
@@ -0,0 +1,580 @@
+//! Serialize module data types to RDB with serde
+//!
+//! Enabled with the `serde` feature. `to_io` and `from_io` can be called in
+//! `rdb_save` and `rdb_load`, or `rtypedef` can generate both methods:
+//! ```rust,no_run
+//! #[derive(Serialize, Deserialize)]
+//! pub struct MyType {
+//!     pub name: String,
+//!     pub scores: Vec<f64>,
+//! }
+//!
+//! #[rtypedef("mytype123", 0, serde)]
+//! impl TypeMethod for MyType {
+//!     fn free(_: Box<Self>) {}
+//! }
+//! ```
+//!
+//! # Encoding
+//!
+//! The encoding is not self describing, the same type must be used to save and
+//! load a value. A change of the type which changes the encoding requires to bump
+//! the encver of the data type.
+//!
+//! | serde type | saved as |
+//! | ---------- | -------- |
+//! | bool | `save_unsigned` of 0 or 1 |
+//! | u8, u16, u32, u64 | `save_unsigned` |
+//! | i8, i16, i32, i64 | `save_signed` |
+//! | f32 | `save_float` |
+//! | f64 | `save_double` |
+//! | char | `save_unsigned` of the code point |
+//! | str, bytes | `save_bytes` |
+//! | option | `save_unsigned` of 0 for none, or 1 followed by the value |
+//! | unit, unit struct | nothing |
+//! | newtype struct | the inner value |
+//! | seq | `save_unsigned` of the length followed by the elements |
+//! | tuple, tuple struct, struct | the fields in order |
+//! | map | `save_unsigned` of the length followed by the keys and values |
+//! | enum | `save_unsigned` of the variant index followed by the variant fields |
+//!
+//! Sequences and maps must know their length before they are serialized,
+//! 128 bits integers are not supported. `to_io` fails for them before anything
+//! is saved.
+
+use crate::error::Error;
+use crate::io::IO;
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::ser::{self, Serialize};
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::new(msg.to_string())
+    }
+}
+
+/// Save a value into the RDB file.
+///
+/// The value is serialized in memory first, so nothing is saved when it fails.
+pub fn to_io<T: Serialize + ?Sized>(io: &mut IO, value: &T) -> Result<(), Error> {
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+    serializer.write_to(io);
+    Ok(())
+}
+
+/// Load a value from the RDB file, that was previously saved with `to_io`
+pub fn from_io<T: DeserializeOwned>(io: &mut IO) -> Result<T, Error> {
+    T::deserialize(&mut Deserializer { io })
+}
+
+/// A value to save into the RDB file
+enum Saved {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f32),
+    Double(f64),
+    Bytes(Vec<u8>),
+}
+
+/// A serde serializer collecting the values to save, see the module documentation
+/// for the encoding
+#[derive(Default)]
+pub struct Serializer {
+    saved: Vec<Saved>,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Serializer { saved: Vec::new() }
+    }
+    /// Save the serialized values into the RDB file
+    pub fn write_to(self, io: &mut IO) {
+        for v in self.saved {
+            match v {
+                Saved::Unsigned(v) => io.save_unsigned(v),
+                Saved::Signed(v) => io.save_signed(v),
+                Saved::Float(v) => io.save_float(v),
+                Saved::Double(v) => io.save_double(v),
+                Saved::Bytes(v) => io.save_bytes(&v),
+            }
+        }
+    }
+    fn save_unsigned(&mut self, v: u64) {
+        self.saved.push(Saved::Unsigned(v));
+    }
+    fn save_len(&mut self, len: Option<usize>) -> Result<(), Error> {
+        let len = len.ok_or_else(|| Error::new("the length of sequence or map is unknown"))?;
+        self.save_unsigned(len as u64);
+        Ok(())
+    }
+}
+
+impl ser::Serializer for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.save_unsigned(v as u64);
+        Ok(())
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(i64::from(v))
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(i64::from(v))
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(i64::from(v))
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.saved.push(Saved::Signed(v));
+        Ok(())
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(u64::from(v))
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(u64::from(v))
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(u64::from(v))
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.save_unsigned(v);
+        Ok(())
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.saved.push(Saved::Float(v));
+        Ok(())
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.saved.push(Saved::Double(v));
+        Ok(())
+    }
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.serialize_u64(v as u64)
+    }
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.saved.push(Saved::Bytes(v.as_bytes().to_vec()));
+        Ok(())
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.saved.push(Saved::Bytes(v.to_vec()));
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_u64(0)
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        self.save_unsigned(1);
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_u32(variant_index)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.save_unsigned(u64::from(variant_index));
+        value.serialize(self)
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, Error> {
+        self.save_len(len)?;
+        Ok(self)
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        self.save_unsigned(u64::from(variant_index));
+        Ok(self)
+    }
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, Error> {
+        self.save_len(len)?;
+        Ok(self)
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        self.save_unsigned(u64::from(variant_index));
+        Ok(self)
+    }
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        key.serialize(&mut **self)
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A serde deserializer reading from `IO`, see the module documentation for the encoding
+pub struct Deserializer<'a> {
+    io: &'a mut IO,
+}
+
+impl<'a> Deserializer<'a> {
+    pub fn new(io: &'a mut IO) -> Self {
+        Deserializer { io }
+    }
+    fn load_len(&mut self) -> Result<usize, Error> {
+        let len = self.io.try_load_unsigned()?;
+        usize::try_from(len).map_err(|_| Error::new(format!("invalid length {}", len)))
+    }
+}
+
+macro_rules! impl_deserialize_integer {
+    ($($method:ident, $visit:ident, $t:ty, $load:ident;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let value = self.io.$load()?;
+                let value = <$t>::try_from(value)
+                    .map_err(|_| Error::new(format!("integer {} is out of range", value)))?;
+                visitor.$visit(value)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a, 'b> de::Deserializer<'de> for &'a mut Deserializer<'b> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(Error::new("the RDB encoding is not self describing"))
+    }
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.io.try_load_unsigned()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            v => Err(Error::new(format!("invalid bool {}", v))),
+        }
+    }
+    impl_deserialize_integer! {
+        deserialize_i8, visit_i8, i8, try_load_signed;
+        deserialize_i16, visit_i16, i16, try_load_signed;
+        deserialize_i32, visit_i32, i32, try_load_signed;
+        deserialize_i64, visit_i64, i64, try_load_signed;
+        deserialize_u8, visit_u8, u8, try_load_unsigned;
+        deserialize_u16, visit_u16, u16, try_load_unsigned;
+        deserialize_u32, visit_u32, u32, try_load_unsigned;
+        deserialize_u64, visit_u64, u64, try_load_unsigned;
+    }
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f32(self.io.try_load_float()?)
+    }
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f64(self.io.try_load_double()?)
+    }
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = self.io.try_load_unsigned()?;
+        let c = u32::try_from(value)
+            .ok()
+            .and_then(std::char::from_u32)
+            .ok_or_else(|| Error::new(format!("invalid char {}", value)))?;
+        visitor.visit_char(c)
+    }
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_string(visitor)
+    }
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let bytes = self.io.load_bytes()?;
+        visitor.visit_string(String::from_utf8(bytes)?)
+    }
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_byte_buf(visitor)
+    }
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_byte_buf(self.io.load_bytes()?)
+    }
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.io.try_load_unsigned()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            v => Err(Error::new(format!("invalid option tag {}", v))),
+        }
+    }
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let len = self.load_len()?;
+        visitor.visit_seq(Access { de: self, len })
+    }
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let len = self.load_len()?;
+        visitor.visit_map(Access { de: self, len })
+    }
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(Access {
+            de: self,
+            len: fields.len(),
+        })
+    }
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self)
+    }
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(Error::new("the RDB encoding has no identifiers"))
+    }
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(Error::new("the RDB encoding is not self describing"))
+    }
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Access the elements of a sequence, tuple, struct or map
+struct Access<'a, 'b> {
+    de: &'a mut Deserializer<'b>,
+    len: usize,
+}
+
+impl<'de, 'a, 'b> SeqAccess<'de> for Access<'a, 'b> {
+    type Error = Error;
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, 'a, 'b> MapAccess<'de> for Access<'a, 'b> {
+    type Error = Error;
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(&mut *self.de)
+    }
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, 'a, 'b> EnumAccess<'de> for &'a mut Deserializer<'b> {
+    type Error = Error;
+    type Variant = Self;
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let index = self.io.try_load_unsigned()?;
+        let index = u32::try_from(index)
+            .map_err(|_| Error::new(format!("invalid variant index {}", index)))?;
+        let value = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a, 'b> VariantAccess<'de> for &'a mut Deserializer<'b> {
+    type Error = Error;
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_seq(Access {
+            de: self,
+            len: fields.len(),
+        })
+    }
+}
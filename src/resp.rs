@@ -0,0 +1,320 @@
+//! Encode and decode the RESP protocol
+//!
+//! The encoder writes a `Value` with the same bytes as `Context::reply`, and the
+//! decoder reads the buffer of `CallReplyRef::get_proto`. Together they allow
+//! to store a reply as bytes, in a module type for example, and to reply with it later.
+//! ```rust,no_run
+//! let reply = call!(ctx, "LRANGE", &args[1], 0, -1)?;
+//! let value = reply.to_value_via_proto()?;
+//! let cached = value.to_resp_bytes(Protocol::Resp2);
+//! // ...
+//! return resp::decode(&cached);
+//! ```
+//!
+//! Like `Context::reply`, a `Value::String` holding "\r", "\n" or NUL is encoded
+//! as a bulk string, and the line breaks of an error are replaced with spaces.
+
+use crate::error::Error;
+use crate::value::Value;
+use crate::RResult;
+
+use std::io::Write;
+
+/// Maximum nesting of aggregates accepted by the decoder
+const MAX_DEPTH: usize = 128;
+
+/// Version of the RESP protocol
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Resp2,
+    Resp3,
+}
+
+/// Encode a value
+pub fn encode(value: &Value, protocol: Protocol) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode_into(&mut buf, value, protocol);
+    buf
+}
+
+/// Encode a result, `Err` is encoded as an error reply
+pub fn encode_result(result: &RResult, protocol: Protocol) -> Vec<u8> {
+    match result {
+        Ok(value) => encode(value, protocol),
+        Err(err) => {
+            let mut buf = Vec::new();
+            write_error(&mut buf, &err.to_string());
+            buf
+        }
+    }
+}
+
+/// Append the encoded value to a buffer
+pub fn encode_into(buf: &mut Vec<u8>, value: &Value, protocol: Protocol) {
+    match value {
+        Value::String(v) if !is_simple(v) => write_bulk(buf, v.as_bytes()),
+        Value::String(v) => {
+            buf.push(b'+');
+            buf.extend_from_slice(v.as_bytes());
+            buf.extend_from_slice(b"\r\n");
+        }
+        Value::BulkString(v) => write_bulk(buf, v),
+        Value::Integer(v) => {
+            write!(buf, ":{}\r\n", v).unwrap();
+        }
+        Value::Double(v) => {
+            let v = format_double(*v);
+            match protocol {
+                Protocol::Resp2 => write_bulk(buf, v.as_bytes()),
+                Protocol::Resp3 => {
+                    write!(buf, ",{}\r\n", v).unwrap();
+                }
+            }
+        }
+        Value::BigNumber(v) => match protocol {
+            Protocol::Resp2 => write_bulk(buf, v.as_bytes()),
+            Protocol::Resp3 => {
+                write!(buf, "({}\r\n", v).unwrap();
+            }
+        },
+        Value::Array(v) => {
+            write!(buf, "*{}\r\n", v.len()).unwrap();
+            for elem in v {
+                encode_into(buf, elem, protocol);
+            }
+        }
+        Value::Null => match protocol {
+            Protocol::Resp2 => buf.extend_from_slice(b"$-1\r\n"),
+            Protocol::Resp3 => buf.extend_from_slice(b"_\r\n"),
+        },
+        Value::NullArray => match protocol {
+            Protocol::Resp2 => buf.extend_from_slice(b"*-1\r\n"),
+            Protocol::Resp3 => buf.extend_from_slice(b"_\r\n"),
+        },
+        Value::Error(v) => write_error(buf, v),
+        Value::NoReply => {}
+    }
+}
+
+/// Decode a buffer holding exactly one reply.
+///
+/// An error reply is returned as `Err`, so the result can be replied as it is,
+/// the errors nested in an array are kept as `Value::Error`.
+/// The RESP3 types are decoded as `CallReply` converts them: maps are
+/// flattened to arrays, sets and pushes become arrays, booleans become integers,
+/// and attributes are skipped.
+///
+/// Aggregates nested deeper than 128 levels are refused.
+pub fn decode(buf: &[u8]) -> RResult {
+    let (value, len) = decode_prefix(buf)?;
+    if len != buf.len() {
+        return Err(invalid("trailing bytes"));
+    }
+    value
+}
+
+/// Decode the first reply of a buffer, also return the count of bytes consumed.
+///
+/// The outer `Err` is for invalid or incomplete buffer, the inner `RResult` is
+/// the decoded reply.
+pub fn decode_prefix(buf: &[u8]) -> Result<(RResult, usize), Error> {
+    let mut decoder = Decoder {
+        buf,
+        pos: 0,
+        depth: 0,
+    };
+    let value = match decoder.read_value()? {
+        Value::Error(msg) => Err(Error::new(msg)),
+        value => Ok(value),
+    };
+    Ok((value, decoder.pos))
+}
+
+fn write_bulk(buf: &mut Vec<u8>, v: &[u8]) {
+    write!(buf, "${}\r\n", v.len()).unwrap();
+    buf.extend_from_slice(v);
+    buf.extend_from_slice(b"\r\n");
+}
+
+/// Whether a string can be a simple string reply
+pub(crate) fn is_simple(v: &str) -> bool {
+    !v.bytes().any(|c| c == b'\r' || c == b'\n' || c == 0)
+}
+
+fn write_error(buf: &mut Vec<u8>, msg: &str) {
+    buf.push(b'-');
+    // Error replies are single line, Redis replaces the line breaks with spaces too
+    buf.extend(
+        msg.bytes()
+            .map(|c| if c == b'\r' || c == b'\n' { b' ' } else { c }),
+    );
+    buf.extend_from_slice(b"\r\n");
+}
+
+/// Format a double like `RedisModule_ReplyWithDouble` does since Redis 7.2, with the
+/// shortest digits reading back the same value, laid out like "%.17g".
+///
+/// Older servers print 17 significant digits, e.g. "0.10000000000000001" instead
+/// of "0.1", both are parsed to the same double.
+fn format_double(v: f64) -> String {
+    if v.is_nan() {
+        return "nan".to_owned();
+    }
+    if v.is_infinite() {
+        return if v > 0.0 { "inf" } else { "-inf" }.to_owned();
+    }
+    // Shortest round trip digits, e.g. "-1.25e-7"
+    let sci = format!("{:e}", v);
+    let (mantissa, exp) = sci.split_at(sci.find('e').unwrap());
+    let exp: i32 = exp[1..].parse().unwrap();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(m) => ("-", m),
+        None => ("", mantissa),
+    };
+    let digits = mantissa.replace('.', "");
+    if !(-4..17).contains(&exp) {
+        let (first, rest) = digits.split_at(1);
+        let dot = if rest.is_empty() { "" } else { "." };
+        let exp_sign = if exp < 0 { '-' } else { '+' };
+        format!(
+            "{}{}{}{}e{}{:02}",
+            sign,
+            first,
+            dot,
+            rest,
+            exp_sign,
+            exp.abs()
+        )
+    } else if exp < 0 {
+        let zeros = "0".repeat((-exp - 1) as usize);
+        format!("{}0.{}{}", sign, zeros, digits)
+    } else {
+        let int_len = exp as usize + 1;
+        if digits.len() <= int_len {
+            format!("{}{}{}", sign, digits, "0".repeat(int_len - digits.len()))
+        } else {
+            let (int, frac) = digits.split_at(int_len);
+            format!("{}{}.{}", sign, int, frac)
+        }
+    }
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::new(format!("Invalid RESP: {}", reason))
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    /// Count of the aggregates being read
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn read_value(&mut self) -> Result<Value, Error> {
+        let (kind, line) = loop {
+            let kind = self.read_byte()?;
+            let line = self.read_line()?;
+            if kind != b'|' {
+                break (kind, line);
+            }
+            // Skip the attribute, the value follows it
+            self.read_elements(parse_pairs(line)?)?;
+        };
+        let value = match kind {
+            b'+' => Value::String(to_str(line)?.to_owned()),
+            b'-' => Value::Error(to_str(line)?.to_owned()),
+            b':' => Value::Integer(parse(line)?),
+            b'$' => match parse::<i64>(line)? {
+                -1 => Value::Null,
+                _ => Value::BulkString(self.read_blob(parse_len(line)?)?.to_vec()),
+            },
+            b'*' | b'~' | b'>' => match parse::<i64>(line)? {
+                -1 => Value::NullArray,
+                _ => self.read_elements(parse_len(line)?)?,
+            },
+            b'%' => self.read_elements(parse_pairs(line)?)?,
+            b'_' => Value::Null,
+            b'#' => match line {
+                b"t" => Value::Integer(1),
+                b"f" => Value::Integer(0),
+                _ => return Err(invalid("bad boolean")),
+            },
+            b',' => Value::Double(parse(line)?),
+            b'(' => Value::BigNumber(to_str(line)?.to_owned()),
+            b'=' => {
+                let blob = self.read_blob(parse_len(line)?)?;
+                // Skip the format, e.g. "txt:"
+                if blob.len() < 4 || blob[3] != b':' {
+                    return Err(invalid("bad verbatim string"));
+                }
+                Value::BulkString(blob[4..].to_vec())
+            }
+            b'!' => Value::Error(to_str(self.read_blob(parse_len(line)?)?)?.to_owned()),
+            _ => return Err(invalid("unknown type")),
+        };
+        Ok(value)
+    }
+    fn read_elements(&mut self, len: usize) -> Result<Value, Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(invalid("too deeply nested"));
+        }
+        self.depth += 1;
+        // Each element takes several bytes, so a bogus length can't allocate more
+        // than the size of the buffer
+        let mut items = Vec::with_capacity(len.min(self.buf.len() - self.pos));
+        for _ in 0..len {
+            items.push(self.read_value()?);
+        }
+        self.depth -= 1;
+        Ok(Value::Array(items))
+    }
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        let c = *self
+            .buf
+            .get(self.pos)
+            .ok_or_else(|| invalid("incomplete"))?;
+        self.pos += 1;
+        Ok(c)
+    }
+    fn read_line(&mut self) -> Result<&'a [u8], Error> {
+        let rest = &self.buf[self.pos..];
+        let end = rest
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(|| invalid("incomplete"))?;
+        self.pos += end + 2;
+        Ok(&rest[..end])
+    }
+    fn read_blob(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let rest = &self.buf[self.pos..];
+        if !matches!(len.checked_add(2), Some(end) if end <= rest.len()) {
+            return Err(invalid("incomplete"));
+        }
+        if &rest[len..len + 2] != b"\r\n" {
+            return Err(invalid("bad blob terminator"));
+        }
+        self.pos += len + 2;
+        Ok(&rest[..len])
+    }
+}
+
+fn to_str(buf: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(buf).map_err(|_| invalid("not utf8"))
+}
+
+/// Parse a length, which can't be negative
+fn parse_len(buf: &[u8]) -> Result<usize, Error> {
+    parse::<usize>(buf).map_err(|_| invalid("bad length"))
+}
+
+/// Parse the length of a map or attribute, the count of its keys and values
+fn parse_pairs(buf: &[u8]) -> Result<usize, Error> {
+    parse_len(buf)?
+        .checked_mul(2)
+        .ok_or_else(|| invalid("bad length"))
+}
+
+fn parse<T: std::str::FromStr>(buf: &[u8]) -> Result<T, Error> {
+    to_str(buf)?.parse::<T>().map_err(|_| invalid("bad number"))
+}
@@ -11,22 +11,20 @@
 //!
 //! #[rtypedef("mytype123", 0)]
 //! impl TypeMethod for MyType {
-//!     fn rdb_load(io: &mut IO, encver: u32) -> Option<Box<Self>> {
+//!     fn rdb_load(io: &mut IO, encver: u32) -> Result<Box<Self>, Error> {
 //!         if encver != 0 {
-//!             return None;
+//!             return Err(Error::new(format!("unknown encver {}", encver)));
 //!         }
-//!         let data = io.load_signed();
-//!         Some(Box::new(MyType { data }))
+//!         let data = io.try_load_signed()?;
+//!         Ok(Box::new(MyType { data }))
 //!     }
 //!     fn rdb_save(&self, io: &mut IO) {
 //!         io.save_signed(self.data);
 //!     }
 //!     fn free(_: Box<Self>) {}
 //!     fn aof_rewrite<T: AsRef<str>>(&self, io: &mut IO, key: T) {
-//!         io.emit_aof(
-//!             "HELLOTYPE.INSERT",
-//!             &[key, self.data.to_string().as_str() ],
-//!         )
+//!         let args: [&dyn ToCallArg; 2] = [&key.as_ref(), &self.data];
+//!         io.emit_aof("MYTYPE.SET", &args)
 //!     }
 //! }
 //!
@@ -43,12 +41,16 @@
 use std::cell::RefCell;
 use std::ffi::CString;
 use std::marker::PhantomData;
-use std::os::raw::c_void;
+use std::ops::{Deref, DerefMut};
+use std::os::raw::{c_int, c_void};
 use std::ptr;
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::time::Duration;
 
 use crate::context::Context;
 use crate::error::Error;
-use crate::io::{Digest, IO};
+use crate::io::{DefragCtx, Digest, KeyOptCtx, IO};
+use crate::key::{ReadKey, WriteKey};
 use crate::raw;
 use crate::string::{RStr, RString};
 use crate::{FromPtr, GetPtr, LogLevel};
@@ -58,9 +60,16 @@ pub trait TypeMethod {
     /// Bit flags control when triggers the aux_load and aux_save callbacks
     const AUX_SAVE_TRIGGERS: AuxSaveTriggerFlag = AuxSaveTriggerFlag::AuxBeforeRdb;
     /// A callback function pointer that loads data from RDB files
+    ///
+    /// When `Err` is returned, the error is logged with `IO::log_io_error` and
+    /// Redis is told the load failed.
+    ///
+    /// The values saved with an encver newer than the one of the data type, or with
+    /// the encver of a `Legacy` encoding listed in the `migrate` attr of `rtypedef`,
+    /// are not loaded by this method.
     #[allow(unused_variables)]
-    fn rdb_load(io: &mut IO, encver: u32) -> Option<Box<Self>> {
-        unreachable!()
+    fn rdb_load(io: &mut IO, encver: u32) -> Result<Box<Self>, Error> {
+        Err(Error::new("rdb_load is not implemented"))
     }
     /// A callback function pointer that saves data to RDB files.
     #[allow(unused_variables)]
@@ -82,18 +91,114 @@ pub trait TypeMethod {
     /// A callback function pointer that can free a type value.
     #[allow(unused_variables)]
     fn free(value: Box<Self>) {}
+    /// A callback function pointer that saves out of keyspace data to RDB files.
+    ///
+    /// It is called at the trigger points of `AUX_SAVE_TRIGGERS`. Use the `aux` attr
+    /// of `rtypedef` to save an `AuxState` instead.
+    #[allow(unused_variables)]
+    fn aux_save(rdb: &mut IO, when: AuxWhen) {}
     /// A callback function pointer that loads out of keyspace data from RDB files.
+    ///
+    /// When `Err` is returned, the error is logged with `IO::log_io_error` and
+    /// Redis is told the load failed.
     #[allow(unused_variables)]
-    fn aux_save(rdb: &mut IO, when: i32) {}
-    /// A callback function pointer that saves out of keyspace data to RDB files.
+    fn aux_load(rdb: &mut IO, encver: u32, when: AuxWhen) -> Result<(), Error> {
+        Ok(())
+    }
+    /// A callback function pointer that returns the effort to free the value, like the
+    /// number of its allocations. The values whose effort is greater than 64 are
+    /// freed in a background thread by `UNLINK` and `FLUSHALL ASYNC`.
+    #[allow(unused_variables)]
+    fn free_effort(&self, key: &RStr) -> usize {
+        1
+    }
+    /// A callback function pointer that is called when the key is removed from the
+    /// keyspace, the value may be freed later in a background thread.
+    #[allow(unused_variables)]
+    fn unlink(&self, key: &RStr) {}
+    /// A callback function pointer that copies the value for the `COPY` command,
+    /// which fails when `None` is returned.
+    #[allow(unused_variables)]
+    fn copy(&self, from_key: &RStr, to_key: &RStr) -> Option<Box<Self>> {
+        None
+    }
+    /// A callback function pointer that is used by active defrag to move the
+    /// allocations of the value with `DefragCtx::alloc`.
+    ///
+    /// Returns true when the value is done. Otherwise the position is saved with
+    /// `DefragCtx::set_cursor`, and the method is called again later.
+    #[allow(unused_variables)]
+    fn defrag(value: &mut Box<Self>, ctx: &mut DefragCtx, key: &RStr) -> bool {
+        true
+    }
+    /// Like `mem_usage`, with the key and the number of elements to sample, which is
+    /// 0 to count all of them. It takes precedence over `mem_usage` since Redis 7.0.
+    #[allow(unused_variables)]
+    fn mem_usage2(&self, ctx: &KeyOptCtx, sample_size: usize) -> usize {
+        0
+    }
+    /// Like `free_effort`, with the key and db. It takes precedence over `free_effort`
+    /// since Redis 7.0.
+    #[allow(unused_variables)]
+    fn free_effort2(&self, ctx: &KeyOptCtx) -> usize {
+        1
+    }
+    /// Like `unlink`, with the key and db. It takes precedence over `unlink` since Redis 7.0.
+    #[allow(unused_variables)]
+    fn unlink2(&self, ctx: &KeyOptCtx) {}
+    /// Like `copy`, with the keys and dbs. It takes precedence over `copy` since Redis 7.0.
     #[allow(unused_variables)]
-    fn aux_load(rdb: &mut IO, encver: u32, when: i32) {}
+    fn copy2(&self, ctx: &KeyOptCtx) -> Option<Box<Self>> {
+        None
+    }
+}
+
+/// A previous encoding of a data type, used to load the RDB files saved by older
+/// versions of the module.
+///
+/// The legacy encodings are listed oldest first in the `migrate` attr of `rtypedef`.
+/// Each of them is upgraded into the next one with `From`, the last one into the data type.
+/// ```rust,no_run
+/// pub struct MyTypeV0 {
+///     pub data: i64,
+/// }
+///
+/// impl Legacy for MyTypeV0 {
+///     const ENCVER: u32 = 0;
+///     fn rdb_load(io: &mut IO) -> Result<Self, Error> {
+///         Ok(MyTypeV0 { data: io.try_load_signed()? })
+///     }
+/// }
+///
+/// impl From<MyTypeV0> for MyType {
+///     fn from(v: MyTypeV0) -> Self {
+///         MyType { data: v.data, label: String::new() }
+///     }
+/// }
+///
+/// #[rtypedef("mytype123", 1, migrate(MyTypeV0))]
+/// impl TypeMethod for MyType {
+///     fn rdb_load(io: &mut IO, _encver: u32) -> Result<Box<Self>, Error> {
+///         let data = io.try_load_signed()?;
+///         let label = io.try_load_string()?;
+///         Ok(Box::new(MyType { data, label }))
+///     }
+///     // ...
+/// }
+/// ```
+pub trait Legacy: Sized {
+    /// The encoding version of the RDB files saved with this encoding
+    const ENCVER: u32;
+    /// Load a value saved with this encoding
+    fn rdb_load(io: &mut IO) -> Result<Self, Error>;
 }
 
 /// Biflags for [`aux_save_triggers`](../raw/struct.RedisModuleTypeMethods.html#structfield.aux_save_triggers)
 pub enum AuxSaveTriggerFlag {
     AuxBeforeRdb = raw::REDISMODULE_AUX_BEFORE_RDB as isize,
     AuxAfterRdb = raw::REDISMODULE_AUX_AFTER_RDB as isize,
+    AuxBeforeAndAfterRdb =
+        (raw::REDISMODULE_AUX_BEFORE_RDB | raw::REDISMODULE_AUX_AFTER_RDB) as isize,
 }
 
 impl Into<i32> for AuxSaveTriggerFlag {
@@ -102,6 +207,136 @@ impl Into<i32> for AuxSaveTriggerFlag {
     }
 }
 
+impl From<AuxWhen> for AuxSaveTriggerFlag {
+    fn from(when: AuxWhen) -> Self {
+        match when {
+            AuxWhen::BeforeRdb => AuxSaveTriggerFlag::AuxBeforeRdb,
+            AuxWhen::AfterRdb => AuxSaveTriggerFlag::AuxAfterRdb,
+        }
+    }
+}
+
+/// The trigger point an aux callback is called at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuxWhen {
+    /// Before the keys of the RDB file
+    BeforeRdb,
+    /// After the keys of the RDB file
+    AfterRdb,
+}
+
+impl From<c_int> for AuxWhen {
+    fn from(when: c_int) -> Self {
+        if when == raw::REDISMODULE_AUX_AFTER_RDB as c_int {
+            AuxWhen::AfterRdb
+        } else {
+            AuxWhen::BeforeRdb
+        }
+    }
+}
+
+/// A module global value persisted in the RDB file, out of the keyspace.
+pub trait AuxData: Default {
+    /// Save the value at a trigger point. Nothing is stored when nothing is saved,
+    /// then `AuxData::aux_load` is not called for that trigger point.
+    fn aux_save(&self, io: &mut IO, when: AuxWhen);
+    /// Load the value saved by `AuxData::aux_save`, with the encver of the data type.
+    fn aux_load(io: &mut IO, encver: u32, when: AuxWhen) -> Result<Self, Error>;
+}
+
+/// A module global value, saved and loaded by the aux callbacks of a data type.
+///
+/// The value is initialized with `Default` and accessed with `Context::aux`, which
+/// fails instead of blocking while the value is already borrowed. It is registered with the `aux` attr of `rtypedef`, and saved
+/// at the trigger points of `TypeMethod::AUX_SAVE_TRIGGERS`.
+///
+/// ```rust,no_run
+/// #[derive(Default)]
+/// pub struct Stats {
+///     pub inserts: u64,
+/// }
+///
+/// impl AuxData for Stats {
+///     fn aux_save(&self, io: &mut IO, _when: AuxWhen) {
+///         io.save_unsigned(self.inserts);
+///     }
+///     fn aux_load(io: &mut IO, _encver: u32, _when: AuxWhen) -> Result<Self, Error> {
+///         Ok(Stats { inserts: io.try_load_unsigned()? })
+///     }
+/// }
+///
+/// pub static STATS: AuxState<Stats> = AuxState::new();
+///
+/// #[rtypedef("mytype123", 0, aux(STATS))]
+/// impl TypeMethod for MyType {
+///     // ...
+/// }
+///
+/// #[rcmd("mytype.insert", "write")]
+/// fn mytype_insert(ctx: &mut Context, args: Vec<RStr>) -> RResult {
+///     // ...
+///     ctx.aux(&STATS)?.inserts += 1;
+///     Ok(Value::NoReply)
+/// }
+/// ```
+pub struct AuxState<T> {
+    value: Mutex<Option<T>>,
+}
+
+impl<T: AuxData> Default for AuxState<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: AuxData> AuxState<T> {
+    pub const fn new() -> Self {
+        AuxState {
+            value: Mutex::new(None),
+        }
+    }
+    pub(crate) fn try_borrow(&self) -> Result<AuxGuard<'_, T>, Error> {
+        let mut guard = match self.value.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::Poisoned(err)) => err.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                return Err(Error::new("aux value is already borrowed"))
+            }
+        };
+        guard.get_or_insert_with(T::default);
+        Ok(AuxGuard { guard })
+    }
+    #[doc(hidden)]
+    pub fn aux_save(&self, io: &mut IO, when: AuxWhen) -> Result<(), Error> {
+        self.try_borrow()?.aux_save(io, when);
+        Ok(())
+    }
+    #[doc(hidden)]
+    pub fn aux_load(&self, io: &mut IO, encver: u32, when: AuxWhen) -> Result<(), Error> {
+        let value = T::aux_load(io, encver, when)?;
+        *self.try_borrow()? = value;
+        Ok(())
+    }
+}
+
+/// A borrow of the value of an `AuxState`, returned by `Context::aux`
+pub struct AuxGuard<'a, T> {
+    guard: MutexGuard<'a, Option<T>>,
+}
+
+impl<T> Deref for AuxGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.guard.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for AuxGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.guard.as_mut().unwrap()
+    }
+}
+
 /// A redis data type
 ///
 /// Recommand creating rtype with `rdeftype` macro and `TypeMethods` trait.
@@ -151,41 +386,90 @@ impl<T> RType<T> {
         }
     }
 
-    /// Decode a serialized representation of a module data type 'mt' from string
-    /// 'str' and return a newly allocated value, or NULL if decoding failed.
+    /// Decode a value from a string serialized by `RType::save`.
     ///
     /// This call basically reuses the 'rdb_load' callback which module data types
     /// implement in order to allow a module to arbitrarily serialize/de-serialize
     /// keys, similar to how the Redis 'DUMP' and 'RESTORE' commands are implemented.
-    pub fn load(&self, value: RStr) -> Box<T> {
-        unsafe {
-            let ptr = raw::RedisModule_LoadDataTypeFromString.unwrap()(
-                value.get_ptr() as *const raw::RedisModuleString,
-                *self.raw_type.borrow_mut(),
-            );
-            Box::from_raw(ptr as *mut T)
+    ///
+    /// The string is loaded with the encver of the type. Redis before 7.2 can only
+    /// load with encver 0, so it fails there for the types with another encver.
+    pub fn load(&self, data: &RStr) -> Result<Box<T>, Error> {
+        let ptr = unsafe {
+            let raw_type = *self.raw_type.borrow();
+            match raw::RedisModule_LoadDataTypeFromStringEncver {
+                Some(load) => load(data.get_ptr(), raw_type, self.version),
+                None if self.version == 0 => {
+                    raw::RedisModule_LoadDataTypeFromString.unwrap()(data.get_ptr(), raw_type)
+                }
+                None => ptr::null_mut(),
+            }
+        };
+        if ptr.is_null() {
+            return Err(Error::new(format!(
+                "fail to load value of type {}",
+                self.name
+            )));
         }
+        Ok(unsafe { Box::from_raw(ptr as *mut T) })
     }
 
-    /// Encode a module data type 'mt' value 'data' into serialized form, and return it
-    /// as a newly allocated RedisModuleString.
+    /// Encode a value into a string, which can be decoded by `RType::load`.
     ///
     /// This call basically reuses the 'rdb_save' callback which module data types
     /// implement in order to allow a module to arbitrarily serialize/de-serialize
     /// keys, similar to how the Redis 'DUMP' and 'RESTORE' commands are implemented.
-    pub fn save(&self, ctx: &Context, value: T) -> Option<(Box<T>, RString)> {
-        let value = Box::into_raw(Box::new(value));
-        unsafe {
-            let ptr = raw::RedisModule_SaveDataTypeToString.unwrap()(
+    pub fn save(&self, ctx: &Context, value: &T) -> Result<RString, Error> {
+        let ptr = unsafe {
+            raw::RedisModule_SaveDataTypeToString.unwrap()(
                 ctx.get_ptr(),
-                value as *mut c_void,
-                *self.raw_type.borrow_mut(),
-            );
-            if ptr.is_null() {
-                return None;
-            }
-            Some((Box::from_raw(value), RString::from_ptr(ptr)))
+                value as *const T as *mut c_void,
+                *self.raw_type.borrow(),
+            )
+        };
+        if ptr.is_null() {
+            return Err(Error::new(format!(
+                "fail to save value of type {}",
+                self.name
+            )));
+        }
+        Ok(RString::from_ptr(ptr))
+    }
+
+    /// Serialize the value of a key, like the `DUMP` command.
+    ///
+    /// Returns `None` when the key is empty, `Error::WrongType` when it holds another type.
+    /// The string is the one of `RType::save`, not the payload of `DUMP`. Like `DUMP`,
+    /// the expire of the key is not included, get it with `ReadKey::get_expire`.
+    pub fn dump_key(&self, ctx: &Context, key: &ReadKey) -> Result<Option<RString>, Error> {
+        match key.get_value(self)? {
+            Some(value) => self.save(ctx, value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Set the value of a key from a string serialized by `RType::dump_key`, like the
+    /// `RESTORE` command.
+    ///
+    /// Unless `replace` is true, the key must be empty. The key expires after `expire`,
+    /// or never when it is `None`, like the TTL of `RESTORE`. Nothing is written when
+    /// the string can't be loaded.
+    pub fn restore_key<'k>(
+        &self,
+        key: &'k mut WriteKey,
+        data: &RStr,
+        expire: Option<Duration>,
+        replace: bool,
+    ) -> Result<&'k mut T, Error> {
+        if !replace && !key.is_empty() {
+            return Err(Error::new("BUSYKEY Target key name already exists."));
+        }
+        let value = self.load(data)?;
+        key.set_value(self, *value)?;
+        if let Some(expire) = expire {
+            key.set_expire(expire)?;
         }
+        Ok(key.get_value_mut(self)?.unwrap())
     }
 
     /// Call ffi to create data type
@@ -3,7 +3,6 @@ use crate::raw;
 use crate::{handle_status, FromPtr, GetPtr};
 
 use crate::error::Error;
-use std::ffi::CString;
 use std::ops::Deref;
 use std::os::raw::c_char;
 use std::str;
@@ -24,16 +23,27 @@ impl FromPtr for RString {
 impl RString {
     /// Generate RString from str
     pub fn from_str<T: AsRef<str>>(value: T) -> RString {
-        let value_ = CString::new(value.as_ref()).unwrap();
+        Self::from_bytes(value.as_ref().as_bytes())
+    }
+    /// Generate RString from a binary buffer
+    pub fn from_bytes<T: AsRef<[u8]>>(value: T) -> RString {
+        let value = value.as_ref();
         let ptr = unsafe {
             raw::RedisModule_CreateString.unwrap()(
-                0 as *mut raw::RedisModuleCtx,
-                value_.as_ptr(),
-                value.as_ref().len(),
+                std::ptr::null_mut(),
+                value.as_ptr() as *const c_char,
+                value.len(),
             )
         };
         Self::from_ptr(ptr)
     }
+    /// Generate RString from an integer
+    pub fn from_integer(value: i64) -> RString {
+        let ptr = unsafe {
+            raw::RedisModule_CreateStringFromLongLong.unwrap()(std::ptr::null_mut(), value)
+        };
+        Self::from_ptr(ptr)
+    }
     /// Get RStr repersentation
     pub fn get_rstr(&self) -> &RStr {
         &self.rstr
@@ -1,4 +1,23 @@
 //! Implement a redis module value
+//!
+//! # Integer conversions
+//!
+//! An integer reply is a signed 64 bits integer. `bool` and the integer types
+//! which always fit (`i8` to `i64`, `isize`, `u16` and `u32`) convert with `From`.
+//! `u64` may not fit and converts with `TryFrom`, failing with an error for
+//! values over `i64::MAX`. `usize` is used for lengths and counts, which never
+//! reach `i64::MAX`, so it converts with `From` and saturates in the impossible case.
+//!
+//! `u8` has no conversion, because `Vec<u8>` and `&[u8]` are bulk strings
+//! and not arrays of integers.
+
+use crate::error::Error;
+use crate::resp::{self, Protocol};
+use crate::string::{RStr, RString};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::iter::FromIterator;
 
 /// Represents the data which will be replied to client
 #[derive(Debug, PartialEq)]
@@ -7,11 +26,26 @@ pub enum Value {
     BulkString(Vec<u8>),
     Integer(i64),
     Double(f64),
+    /// The decimal digits of a RESP3 big number, a bulk string in RESP2
+    BigNumber(String),
+    /// An empty array is `Array(vec![])`
     Array(Vec<Value>),
+    /// The null bulk string, "$-1" in RESP2
     Null,
+    /// The null array, "*-1" in RESP2, e.g. the reply of `BLPOP` on timeout
+    NullArray,
+    /// An error reply, mostly nested in an array since a top level error is `Err`
+    Error(String),
     NoReply,
 }
 
+impl Value {
+    /// Encode the value with RESP, see `resp::encode`
+    pub fn to_resp_bytes(&self, protocol: Protocol) -> Vec<u8> {
+        resp::encode(self, protocol)
+    }
+}
+
 impl From<()> for Value {
     fn from(_: ()) -> Self {
         Value::Null
@@ -26,7 +60,48 @@ impl From<i64> for Value {
 
 impl From<usize> for Value {
     fn from(i: usize) -> Self {
-        (i as i64).into()
+        i64::try_from(i).unwrap_or(i64::MAX).into()
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Integer(b as i64)
+    }
+}
+
+macro_rules! impl_from_integer {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Value {
+                fn from(i: $t) -> Self {
+                    Value::Integer(i64::from(i))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_integer!(i8, i16, i32, u16, u32);
+
+impl From<isize> for Value {
+    fn from(i: isize) -> Self {
+        Value::Integer(i as i64)
+    }
+}
+
+impl TryFrom<u64> for Value {
+    type Error = Error;
+    fn try_from(i: u64) -> Result<Self, Error> {
+        i64::try_from(i)
+            .map(Value::Integer)
+            .map_err(|_| Error::new("ERR value is out of range of integer reply"))
+    }
+}
+
+impl From<f32> for Value {
+    fn from(f: f32) -> Self {
+        Value::Double(f64::from(f))
     }
 }
 
@@ -60,6 +135,30 @@ impl From<Vec<u8>> for Value {
     }
 }
 
+impl From<&[u8]> for Value {
+    fn from(b: &[u8]) -> Self {
+        Value::BulkString(b.to_vec())
+    }
+}
+
+impl From<&RStr> for Value {
+    fn from(s: &RStr) -> Self {
+        s.get_buffer().into()
+    }
+}
+
+impl From<RString> for Value {
+    fn from(s: RString) -> Self {
+        s.get_rstr().into()
+    }
+}
+
+impl From<&RString> for Value {
+    fn from(s: &RString) -> Self {
+        s.get_rstr().into()
+    }
+}
+
 impl<T: Into<Value>> From<Option<T>> for Value {
     fn from(s: Option<T>) -> Self {
         match s {
@@ -74,3 +173,78 @@ impl<T: Into<Value>> From<Vec<T>> for Value {
         Value::Array(items.into_iter().map(|item| item.into()).collect())
     }
 }
+
+impl<T: Into<Value>> From<VecDeque<T>> for Value {
+    fn from(items: VecDeque<T>) -> Self {
+        items.into_iter().collect()
+    }
+}
+
+impl<T: Into<Value>> From<HashSet<T>> for Value {
+    fn from(items: HashSet<T>) -> Self {
+        items.into_iter().collect()
+    }
+}
+
+impl<T: Into<Value>> From<BTreeSet<T>> for Value {
+    fn from(items: BTreeSet<T>) -> Self {
+        items.into_iter().collect()
+    }
+}
+
+/// A map is replied as an array of keys and values, like `HGETALL`
+impl<K: Into<Value>, V: Into<Value>> From<HashMap<K, V>> for Value {
+    fn from(map: HashMap<K, V>) -> Self {
+        flatten_map(map)
+    }
+}
+
+/// A map is replied as an array of keys and values, like `HGETALL`
+impl<K: Into<Value>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        flatten_map(map)
+    }
+}
+
+fn flatten_map<K, V, I>(map: I) -> Value
+where
+    K: Into<Value>,
+    V: Into<Value>,
+    I: IntoIterator<Item = (K, V)>,
+{
+    let mut items = vec![];
+    for (key, value) in map {
+        items.push(key.into());
+        items.push(value.into());
+    }
+    Value::Array(items)
+}
+
+macro_rules! impl_from_tuple {
+    ($(($($name:ident),+)),*) => {
+        $(
+            /// A tuple is replied as an array
+            impl<$($name: Into<Value>),+> From<($($name,)+)> for Value {
+                #[allow(non_snake_case)]
+                fn from(($($name,)+): ($($name,)+)) -> Self {
+                    Value::Array(vec![$($name.into()),+])
+                }
+            }
+        )*
+    };
+}
+
+impl_from_tuple!(
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F)
+);
+
+/// Collect the items to an array
+impl<V: Into<Value>> FromIterator<V> for Value {
+    fn from_iter<I: IntoIterator<Item = V>>(iter: I) -> Self {
+        Value::Array(iter.into_iter().map(|item| item.into()).collect())
+    }
+}